@@ -3,17 +3,41 @@
 use super::super::info::{TokenData, TokenPostData};
 use super::{client::request_impl, client::Message, error::SirixError};
 use hyper::http::uri::{Authority, PathAndQuery, Scheme};
-use hyper::{header::HeaderValue, http::uri::InvalidUri, Body, HeaderMap, Method, Uri};
-use log::{error, info};
+use hyper::{header::HeaderValue, Body, HeaderMap, Method, Uri};
+use log::{error, info, warn};
 use serde_json::ser::to_string;
 use std::sync::Arc;
 use tokio::sync::{mpsc, watch, Notify};
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, Duration, Instant};
 
-async fn authenticate(
-    channel: mpsc::Sender<Message>,
+/// The URL to request tokens from
+#[derive(Debug, Clone)]
+struct TokenEndpoint {
     scheme: Scheme,
     authority: Authority,
+    path_and_query: PathAndQuery,
+}
+
+impl TokenEndpoint {
+    fn parse(url: &str) -> Result<Self, SirixError> {
+        let parsed = url.parse::<Uri>()?;
+        match (parsed.scheme(), parsed.authority()) {
+            (Some(scheme), Some(authority)) => Ok(Self {
+                scheme: scheme.clone(),
+                authority: authority.clone(),
+                path_and_query: parsed
+                    .path_and_query()
+                    .cloned()
+                    .unwrap_or_else(|| PathAndQuery::from_static("/")),
+            }),
+            _ => Err(SirixError::IncompleteUri(url.to_owned())),
+        }
+    }
+}
+
+async fn authenticate(
+    channel: mpsc::Sender<Message>,
+    endpoint: &TokenEndpoint,
     username: &str,
     password: &str,
 ) -> Result<TokenData, SirixError> {
@@ -21,19 +45,16 @@ async fn authenticate(
     header_map.append("content-type", HeaderValue::from_static("application/json"));
     let response = request_impl::<TokenData>(
         channel,
-        scheme,
-        authority,
-        PathAndQuery::from_static("/token"),
+        endpoint.scheme.clone(),
+        endpoint.authority.clone(),
+        endpoint.path_and_query.clone(),
         Method::POST,
         header_map,
-        Body::from(
-            to_string(&TokenPostData {
-                username: username.to_string(),
-                password: password.to_string(),
-                grant_type: "password".to_string(),
-            })
-            .unwrap(),
-        ),
+        Body::from(to_string(&TokenPostData {
+            username: username.to_string(),
+            password: password.to_string(),
+            grant_type: "password".to_string(),
+        })?),
     )
     .await;
     match response {
@@ -44,8 +65,7 @@ async fn authenticate(
 
 async fn refresh(
     channel: mpsc::Sender<Message>,
-    scheme: Scheme,
-    authority: Authority,
+    endpoint: &TokenEndpoint,
     token_data: TokenData,
 ) -> Result<TokenData, SirixError> {
     let mut header_map = HeaderMap::new();
@@ -53,9 +73,9 @@ async fn refresh(
     let refresh_json = format!(r#"{{"refresh_token":"{}"}}"#, token_data.refresh_token);
     let response = request_impl::<TokenData>(
         channel,
-        scheme,
-        authority,
-        PathAndQuery::from_static("/token"),
+        endpoint.scheme.clone(),
+        endpoint.authority.clone(),
+        endpoint.path_and_query.clone(),
         Method::POST,
         header_map,
         Body::from(refresh_json),
@@ -67,96 +87,184 @@ async fn refresh(
     }
 }
 
+/// The state of the background authentication task
+#[derive(Debug, Clone)]
+pub enum AuthState {
+    /// the first login with credentials has not completed yet
+    Authenticating,
+    /// a valid token is available
+    Authenticated,
+    /// the token is about to expire and is being renewed
+    Refreshing,
+    /// the last attempt to obtain a token failed, and will be retried
+    Failed(Arc<SirixError>),
+}
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Obtain a new token, preferring the refresh token while it is still valid.
+async fn renew(
+    channel: mpsc::Sender<Message>,
+    endpoint: &TokenEndpoint,
+    username: &str,
+    password: &str,
+    token_data: &TokenData,
+    issued_at: Instant,
+) -> Result<TokenData, SirixError> {
+    if token_data.refresh_token_valid(issued_at.elapsed()) {
+        match refresh(channel.clone(), endpoint, token_data.clone()).await {
+            Ok(token_data) => return Ok(token_data),
+            Err(err) => warn!(
+                "token refresh failed, authenticating with credentials: {}",
+                err
+            ),
+        }
+    } else {
+        info!("refresh token expired, authenticating with credentials");
+    }
+    authenticate(channel, endpoint, username, password).await
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn begin_authentication_loop(
     channel: mpsc::Sender<Message>,
-    scheme: &Scheme,
-    authority: &Authority,
+    endpoint: &TokenEndpoint,
     username: &str,
     password: &str,
+    mut token_data: TokenData,
     loop_kill_receiver: Arc<Notify>,
-    response_sender: watch::Sender<Option<TokenData>>,
-) -> () {
-    let token_data = authenticate(
-        channel.clone(),
-        scheme.clone(),
-        authority.clone(),
-        username,
-        password,
-    )
-    .await;
-    let token_data = match token_data {
-        Ok(token_data) => {
-            info!("authentication with credentials successful");
-            response_sender.send(Some(token_data.clone())).unwrap();
-            Some(token_data)
-        }
-        Err(_) => {
-            error!("authentication with credentials failed");
-            None
-        }
-    };
-    let mut token_data = token_data.clone().unwrap();
+    token_sender: watch::Sender<Option<TokenData>>,
+    state_sender: watch::Sender<AuthState>,
+) {
+    let mut issued_at = Instant::now();
     loop {
         tokio::select! {
-            _ = sleep(Duration::from_secs(token_data.expires_in.clone() - 10)) => (),
+            _ = sleep(token_data.refresh_delay()) => (),
             _ = loop_kill_receiver.notified() => {break},
         };
-        let refresh_response = refresh(
+        let _ = state_sender.send(AuthState::Refreshing);
+        let mut result = renew(
             channel.clone(),
-            scheme.clone(),
-            authority.clone(),
-            token_data.clone(),
+            endpoint,
+            username,
+            password,
+            &token_data,
+            issued_at,
         )
         .await;
-        match refresh_response {
-            Ok(new_token_data) => {
-                info!("authentication with credentials successful");
-                token_data = new_token_data;
-                response_sender.send(Some(token_data.clone())).unwrap();
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            match result {
+                Ok(new_token_data) => {
+                    info!("token renewal successful");
+                    token_data = new_token_data;
+                    issued_at = Instant::now();
+                    let _ = token_sender.send(Some(token_data.clone()));
+                    let _ = state_sender.send(AuthState::Authenticated);
+                    break;
+                }
+                Err(err) => {
+                    error!("token renewal failed, retrying in {:?}: {}", backoff, err);
+                    let _ = state_sender.send(AuthState::Failed(Arc::new(err)));
+                    tokio::select! {
+                        _ = sleep(backoff) => (),
+                        _ = loop_kill_receiver.notified() => {return},
+                    };
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    result = authenticate(channel.clone(), endpoint, username, password).await;
+                }
             }
-            Err(_) => {
-                error!("authentication with credentials failed")
-            }
-        };
+        }
+    }
+}
+
+/// The `authorization` header for the current token, if the client authenticates
+pub(crate) fn authorization(
+    watcher: &Option<watch::Receiver<Option<TokenData>>>,
+) -> Result<Option<String>, SirixError> {
+    match watcher {
+        Some(watcher) => match watcher.borrow().as_ref() {
+            Some(token_data) => Ok(Some(token_data.authorization())),
+            None => Err(SirixError::NotAuthenticated),
+        },
+        None => Ok(None),
     }
 }
 
+/// Log in with the given credentials and keep the token fresh in a background task.
+///
+/// Fails if the first login is rejected. The returned `Notify` stops the background task.
 pub async fn auth(
     username: &str,
     password: &str,
     base_url: &str,
     channel: mpsc::Sender<Message>,
-) -> Result<(watch::Receiver<std::option::Option<TokenData>>, Arc<Notify>), InvalidUri> {
+) -> Result<
+    (
+        watch::Receiver<Option<TokenData>>,
+        watch::Receiver<AuthState>,
+        Arc<Notify>,
+    ),
+    SirixError,
+> {
     // validate that passed URL is valid
-    let base_url = base_url.to_owned();
-    base_url.parse::<Uri>()?;
+    let parsed = base_url.parse::<Uri>()?;
+    if parsed.scheme().is_none() || parsed.authority().is_none() {
+        return Err(SirixError::IncompleteUri(base_url.to_owned()));
+    }
+    let endpoint = format!("{}/token", base_url.trim_end_matches('/'));
+    start_auth(channel, &endpoint, username, password).await
+}
+
+/// Log in at the given token endpoint and start the background refresh task.
+pub(crate) async fn start_auth(
+    channel: mpsc::Sender<Message>,
+    endpoint: &str,
+    username: &str,
+    password: &str,
+) -> Result<
+    (
+        watch::Receiver<Option<TokenData>>,
+        watch::Receiver<AuthState>,
+        Arc<Notify>,
+    ),
+    SirixError,
+> {
+    let endpoint = TokenEndpoint::parse(endpoint)?;
+    let (state_tx, state_rx) = watch::channel(AuthState::Authenticating);
+    let token_data = match authenticate(channel.clone(), &endpoint, username, password).await {
+        Ok(token_data) => {
+            info!("authentication with credentials successful");
+            let _ = state_tx.send(AuthState::Authenticated);
+            token_data
+        }
+        Err(err) => {
+            error!("authentication with credentials failed: {}", err);
+            return Err(err);
+        }
+    };
 
     let username = username.to_owned();
     let password = password.to_owned();
 
-    let (watch_tx, mut watch_rx) = watch::channel::<Option<TokenData>>(None);
+    let (watch_tx, watch_rx) = watch::channel(Some(token_data.clone()));
     let kill_switch = Arc::new(Notify::new());
     let kill_switch_receiver = kill_switch.clone();
     tokio::spawn(async move {
-        let parsed = base_url.parse::<Uri>().unwrap();
-        let authority = parsed.authority().unwrap();
-        let scheme = parsed.scheme().unwrap();
         begin_authentication_loop(
             channel,
-            scheme,
-            authority,
+            &endpoint,
             &username,
             &password,
+            token_data,
             kill_switch_receiver,
             watch_tx,
+            state_tx,
         )
         .await;
     });
-    tokio::select! {
-        _ = watch_rx.changed() => {},
-        _ = sleep(Duration::from_secs(5)) => {}
-    };
-    return Ok((watch_rx, kill_switch));
+    Ok((watch_rx, state_rx, kill_switch))
 }
 
 #[cfg(test)]
@@ -164,7 +272,6 @@ mod tests {
     use super::super::super::mock::test_mocks;
     use super::super::client::spawn_client;
     use super::*;
-    use hyper::http::uri::Uri;
     use mockito;
     #[tokio::test]
     async fn test_authenticate() {
@@ -174,15 +281,8 @@ mod tests {
         let (sender, receiver) = tokio::sync::mpsc::channel(32);
         spawn_client(hyper::Client::new(), receiver);
         // test authenticate
-        let parsed = url.parse::<Uri>().unwrap();
-        let response = authenticate(
-            sender,
-            parsed.scheme().unwrap().to_owned(),
-            parsed.authority().unwrap().to_owned(),
-            "admin",
-            "admin",
-        )
-        .await;
+        let endpoint = TokenEndpoint::parse(&format!("{}/token", url)).unwrap();
+        let response = authenticate(sender, &endpoint, "admin", "admin").await;
         assert_eq!(response.unwrap(), test_mocks::get_token_data());
     }
     #[tokio::test]
@@ -193,28 +293,22 @@ mod tests {
         let _m2 = test_mocks::mock_refresh();
         let (sender, receiver) = tokio::sync::mpsc::channel(32);
         spawn_client(hyper::Client::new(), receiver);
-        let parsed = url.parse::<Uri>().unwrap();
-        let response = authenticate(
-            sender.clone(),
-            parsed.scheme().unwrap().to_owned(),
-            parsed.authority().unwrap().to_owned(),
-            "admin",
-            "admin",
-        )
-        .await;
+        let endpoint = TokenEndpoint::parse(&format!("{}/token", url)).unwrap();
+        let response = authenticate(sender.clone(), &endpoint, "admin", "admin").await;
         let token_data = response.unwrap();
         assert_eq!(token_data.clone(), test_mocks::get_token_data());
         // test refresh
-        let refresh_response = refresh(
-            sender,
-            parsed.scheme().unwrap().to_owned(),
-            parsed.authority().unwrap().to_owned(),
-            token_data,
-        )
-        .await;
+        let refresh_response = refresh(sender, &endpoint, token_data).await;
         assert_ne!(refresh_response.unwrap(), test_mocks::get_token_data());
     }
     #[tokio::test]
+    async fn test_auth_incomplete_uri() {
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let response = auth("admin", "admin", "localhost:9443", sender).await;
+        assert!(matches!(response, Err(SirixError::IncompleteUri(_))));
+    }
+    #[tokio::test]
     async fn test_auth_func() {
         // setup mocks
         let url = &mockito::server_url();
@@ -224,16 +318,19 @@ mod tests {
         let (sender, receiver) = tokio::sync::mpsc::channel(32);
         spawn_client(hyper::Client::new(), receiver);
         // initiate auth coroutine
-        let (watch_rx, kill_switch) = auth("admin", "admin", url, sender).await.unwrap();
+        let (mut watch_rx, state_rx, kill_switch) =
+            auth("admin", "admin", url, sender).await.unwrap();
+        assert!(matches!(*state_rx.borrow(), AuthState::Authenticated));
         assert_eq!(
             *watch_rx.borrow().as_ref().unwrap(),
             test_mocks::get_token_data()
         );
-        // advance time to refresh
+        // let the auth task start waiting, then advance time to refresh
+        tokio::task::yield_now().await;
         tokio::time::pause();
         tokio::time::advance(Duration::from_secs(test_mocks::get_token_data().expires_in)).await;
         tokio::time::resume();
-        tokio::time::sleep(Duration::from_millis(10)).await;
+        watch_rx.changed().await.unwrap();
         // check that auth token was replaced
         assert_ne!(
             *watch_rx.borrow().as_ref().unwrap(),
@@ -242,4 +339,39 @@ mod tests {
         // shut down auth coroutine
         kill_switch.notify_one();
     }
+    #[tokio::test]
+    async fn test_auth_failure() {
+        let url = &mockito::server_url();
+        let _m = mockito::mock("POST", "/token").with_status(401).create();
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let response = auth("admin", "admin", url, sender).await;
+        assert!(matches!(response, Err(SirixError::Unauthorized)));
+    }
+    #[tokio::test]
+    async fn test_renewal_backoff() {
+        // the first login succeeds, everything after it fails
+        let url = &mockito::server_url();
+        let m = test_mocks::mock_auth().expect(1);
+        let _m2 = mockito::mock("POST", "/token").with_status(503).create();
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let (watch_rx, mut state_rx, kill_switch) =
+            auth("admin", "admin", url, sender).await.unwrap();
+        // let the auth task start waiting, then advance time to refresh
+        tokio::task::yield_now().await;
+        tokio::time::pause();
+        tokio::time::advance(Duration::from_secs(test_mocks::get_token_data().expires_in)).await;
+        tokio::time::resume();
+        while !matches!(*state_rx.borrow(), AuthState::Failed(_)) {
+            state_rx.changed().await.unwrap();
+        }
+        // the previous token is kept while retrying
+        assert_eq!(
+            *watch_rx.borrow().as_ref().unwrap(),
+            test_mocks::get_token_data()
+        );
+        kill_switch.notify_one();
+        m.assert();
+    }
 }
@@ -0,0 +1,285 @@
+//! This module contains a builder for configuring a `Sirix` client
+
+use super::auth::start_auth;
+use super::client::{spawn_client_with_options, ClientOptions};
+use super::error::SirixError;
+use super::sirix::Sirix;
+#[cfg(feature = "rustls")]
+use super::tls::TlsConfig;
+use super::SirixResult;
+use hyper::header::{HeaderName, HeaderValue, USER_AGENT};
+use hyper::{client::HttpConnector, Client, HeaderMap, Uri};
+use std::sync::Arc;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+/// Configures and connects a `Sirix` client.
+///
+/// ```no_run
+/// # async fn connect() {
+/// use sirix_rust_client::asynchronous::sirix::Sirix;
+///
+/// let sirix = Sirix::builder("http://localhost:9443")
+///     .credentials("admin", "admin")
+///     .build()
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SirixBuilder {
+    base_url: String,
+    credentials: Option<(String, String)>,
+    token_endpoint: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    channel_capacity: usize,
+    user_agent: Option<String>,
+    default_headers: Vec<(String, String)>,
+    #[cfg(feature = "rustls")]
+    tls: TlsConfig,
+}
+
+/// The tasks started by `SirixBuilder::build`
+#[derive(Debug)]
+pub(crate) struct BackgroundTasks {
+    client: JoinHandle<()>,
+    kill_switch: Option<Arc<Notify>>,
+}
+
+impl Drop for BackgroundTasks {
+    fn drop(&mut self) {
+        if let Some(kill_switch) = &self.kill_switch {
+            kill_switch.notify_one();
+        }
+        self.client.abort();
+    }
+}
+
+impl SirixBuilder {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_owned(),
+            credentials: None,
+            token_endpoint: None,
+            timeout: None,
+            connect_timeout: None,
+            channel_capacity: 32,
+            user_agent: None,
+            default_headers: Vec::new(),
+            #[cfg(feature = "rustls")]
+            tls: TlsConfig::default(),
+        }
+    }
+
+    /// The URL of the SirixDB server
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_owned();
+        self
+    }
+
+    /// Log in with these credentials, and keep the token fresh in the background
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_owned(), password.to_owned()));
+        self
+    }
+
+    /// The URL to request tokens from, `<base_url>/token` by default
+    pub fn token_endpoint(mut self, token_endpoint: &str) -> Self {
+        self.token_endpoint = Some(token_endpoint.to_owned());
+        self
+    }
+
+    /// The maximum time to wait for the response headers.
+    ///
+    /// Streamed uploads are not limited, only the wait for the response once they are sent.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The maximum time to wait for a connection to the server
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// How many requests may queue up for the client task, 32 by default
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = capacity;
+        self
+    }
+
+    /// The value of the `user-agent` header
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_owned());
+        self
+    }
+
+    /// A header to send with every request that does not already set it
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        self.default_headers
+            .push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// TLS settings for `https` URLs
+    #[cfg(feature = "rustls")]
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Start the client task and create the client, logging in first if credentials
+    /// were given.
+    pub async fn build(self) -> SirixResult<Sirix> {
+        let base_url = self.base_url.trim_end_matches('/');
+        let parsed = base_url.parse::<Uri>()?;
+        let (scheme, authority) = match (parsed.scheme(), parsed.authority()) {
+            (Some(scheme), Some(authority)) => (scheme.clone(), authority.clone()),
+            _ => return Err(SirixError::IncompleteUri(base_url.to_owned())),
+        };
+
+        let mut default_headers = HeaderMap::new();
+        for (name, value) in &self.default_headers {
+            default_headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        if let Some(user_agent) = &self.user_agent {
+            default_headers.insert(USER_AGENT, HeaderValue::from_str(user_agent)?);
+        }
+
+        let mut connector = HttpConnector::new();
+        connector.set_connect_timeout(self.connect_timeout);
+        let (sender, receiver) = mpsc::channel(self.channel_capacity.max(1));
+        #[cfg(feature = "rustls")]
+        let connector = self.tls.https_connector(connector)?;
+        let mut tasks = BackgroundTasks {
+            client: spawn_client_with_options(
+                Client::builder().build(connector),
+                receiver,
+                ClientOptions {
+                    default_headers,
+                    timeout: self.timeout,
+                },
+            ),
+            kill_switch: None,
+        };
+
+        let auth = match &self.credentials {
+            Some((username, password)) => {
+                let token_endpoint = match &self.token_endpoint {
+                    Some(token_endpoint) => token_endpoint.clone(),
+                    None => format!("{}/token", base_url),
+                };
+                // on failure, dropping `tasks` stops the client task
+                let (token_rx, state_rx, kill_switch) =
+                    start_auth(sender.clone(), &token_endpoint, username, password).await?;
+                tasks.kill_switch = Some(kill_switch);
+                Some((token_rx, state_rx))
+            }
+            None => None,
+        };
+        Ok(Sirix::with_tasks(scheme, authority, sender, auth, tasks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::super::auth::AuthState;
+    use super::*;
+    use mockito::mock;
+
+    #[tokio::test]
+    async fn test_build() {
+        let url = &mockito::server_url();
+        let _m = test_mocks::mock_auth();
+        let _m2 = mock("GET", "/")
+            .match_header(
+                "authorization",
+                format!("bearer {}", test_mocks::get_token_data().access_token).as_str(),
+            )
+            .match_header("user-agent", "sirix-test")
+            .match_header("x-tenant", "test")
+            .with_body(r#"[]"#)
+            .create();
+
+        let sirix = Sirix::builder(url)
+            .credentials("admin", "admin")
+            .user_agent("sirix-test")
+            .default_header("x-tenant", "test")
+            .build()
+            .await
+            .unwrap();
+        assert!(matches!(sirix.auth_state(), Some(AuthState::Authenticated)));
+        assert!(sirix.info().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_database_outlives_client() {
+        let url = &mockito::server_url();
+        let _m = test_mocks::mock_auth();
+        let _m2 = mock("GET", "/outlives-async")
+            .match_header(
+                "authorization",
+                format!("bearer {}", test_mocks::get_token_data().access_token).as_str(),
+            )
+            .with_body(r#"{"resources":[]}"#)
+            .create();
+
+        let sirix = Sirix::builder(url)
+            .credentials("admin", "admin")
+            .build()
+            .await
+            .unwrap();
+        let database = sirix.json_database("outlives-async".to_owned());
+        drop(sirix);
+        assert!(database.info().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_build_token_endpoint() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/auth/token")
+            .with_body(serde_json::to_string(&test_mocks::get_token_data()).unwrap())
+            .create();
+
+        let sirix = Sirix::builder(url)
+            .credentials("admin", "admin")
+            .token_endpoint(&format!("{}/auth/token", url))
+            .build()
+            .await
+            .unwrap();
+        assert!(matches!(sirix.auth_state(), Some(AuthState::Authenticated)));
+    }
+
+    #[tokio::test]
+    async fn test_build_auth_failure() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/token").with_status(401).create();
+
+        let response = Sirix::builder(url)
+            .credentials("admin", "admin")
+            .build()
+            .await;
+        assert!(matches!(response, Err(SirixError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        // accepts connections, but never responds
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let sirix = Sirix::builder(&url)
+            .timeout(Duration::from_millis(50))
+            .build()
+            .await
+            .unwrap();
+        assert!(matches!(sirix.info().await, Err(SirixError::Timeout)));
+    }
+}
@@ -1,18 +1,29 @@
+use super::super::transport::{self, SirixRequest, Transport};
+use super::builder::BackgroundTasks;
 use super::error::SirixError;
+use super::upload::SentBody;
 use super::SirixResult;
+use hyper::body::{self, Buf, HttpBody};
+use hyper::header::{HeaderName, HeaderValue};
 use hyper::http::status::StatusCode;
 use hyper::http::uri::{Authority, PathAndQuery, Scheme};
-use hyper::{
-    body, body::Buf, header::HeaderValue, Body, Error, HeaderMap, Method, Request, Response, Uri,
-};
-use hyper::{client::HttpConnector, Client};
+use hyper::{client::connect::Connect, Client};
+use hyper::{Body, HeaderMap, Method, Request, Response, Uri};
 use serde::de::DeserializeOwned;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
 use tokio::select;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
+use tokio::time::{timeout, Duration};
 
-pub type ResultResponse = Result<Response<Body>, Error>;
+pub type ResultResponse = SirixResult<Response<Body>>;
+
+/// A response from SirixDB that has not arrived yet
+pub type ResponseFuture<T> = Pin<Box<dyn Future<Output = SirixResult<SirixResponse<T>>> + Send>>;
 
 #[derive(Debug)]
 pub struct SirixResponse<T> {
@@ -27,27 +38,103 @@ pub struct Message {
     pub responder: oneshot::Sender<ResultResponse>,
 }
 
-pub fn spawn_client(
-    client: Client<HttpConnector>,
+/// Settings the client task applies to every request
+#[derive(Debug, Clone, Default)]
+pub struct ClientOptions {
+    /// headers added to each request that does not already set them
+    pub default_headers: HeaderMap,
+    /// the maximum time to wait for the response headers, after a streamed body was sent
+    pub timeout: Option<Duration>,
+}
+
+/// Spawn the task that sends requests received on `channel`.
+///
+/// Accepts any connector, such as an `HttpsConnector` with the `rustls` feature.
+pub fn spawn_client<C>(client: Client<C>, channel: Receiver<Message>) -> JoinHandle<()>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    spawn_client_with_options(client, channel, ClientOptions::default())
+}
+
+pub fn spawn_client_with_options<C>(
+    client: Client<C>,
     mut channel: Receiver<Message>,
-) -> JoinHandle<()> {
+    options: ClientOptions,
+) -> JoinHandle<()>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
     tokio::spawn(async move {
         loop {
-            let message = select! {
+            let mut message = select! {
                 // if the channel sent a message, get the message and send the HTTP request
                 Some(message) = channel.recv() => message,
                 // if the channel closed, break the loop, and the task will terminate
                 else => {break},
             };
-            // make the HTTP request
-            let response = client.request(message.request).await;
-            // return the response (or error) to the caller
-            message.responder.send(response).unwrap();
+            for (name, value) in options.default_headers.iter() {
+                if !message.request.headers().contains_key(name) {
+                    message
+                        .request
+                        .headers_mut()
+                        .insert(name.clone(), value.clone());
+                }
+            }
+            // make the HTTP request in its own task, so a long upload does not hold up the others
+            tokio::spawn(respond(client.clone(), message, options.timeout));
         }
     })
 }
 
-pub async fn request_impl<T: DeserializeOwned>(
+/// Send the request of `message`, and return the response (or error) to its caller,
+/// unless it stopped waiting
+async fn respond<C>(client: Client<C>, message: Message, duration: Option<Duration>)
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    let response = match duration {
+        Some(duration) => timed_request(&client, message.request, duration).await,
+        None => client
+            .request(message.request)
+            .await
+            .map_err(SirixError::from),
+    };
+    let _ = message.responder.send(response);
+}
+
+/// Send `request`, waiting at most `duration` for the response headers.
+///
+/// A streamed body may take much longer to send than the response takes to arrive,
+/// so the time only counts once all of it was sent.
+async fn timed_request<C>(
+    client: &Client<C>,
+    request: Request<Body>,
+    duration: Duration,
+) -> ResultResponse
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    let response = if request.body().size_hint().exact().is_some() {
+        timeout(duration, client.request(request)).await
+    } else {
+        let (parts, body) = request.into_parts();
+        let (body, sent) = SentBody::new(body);
+        let mut response = client.request(Request::from_parts(parts, Body::wrap_stream(body)));
+        select! {
+            // the server may respond before the body was sent, to reject it
+            response = &mut response => return response.map_err(SirixError::from),
+            _ = sent => timeout(duration, response).await,
+        }
+    };
+    match response {
+        Ok(response) => response.map_err(SirixError::from),
+        Err(_) => Err(SirixError::Timeout),
+    }
+}
+
+/// Send a request through the client task and wait for its response.
+async fn send_request(
     channel: Sender<Message>,
     scheme: Scheme,
     authority: Authority,
@@ -55,30 +142,49 @@ pub async fn request_impl<T: DeserializeOwned>(
     method: Method,
     headers: HeaderMap,
     body: Body,
-) -> SirixResult<SirixResponse<T>> {
+) -> SirixResult<Response<Body>> {
     let uri = Uri::builder()
         .scheme(scheme)
         .authority(authority)
         .path_and_query(path_and_query)
-        .build()
-        .unwrap();
+        .build()?;
     // create request
-    let mut request_builder = Request::builder().uri(uri).method(method);
-    for header in headers {
-        request_builder = request_builder.header(header.0.unwrap(), header.1);
-    }
-    let request = request_builder.body(body).unwrap();
+    let mut request = Request::builder().uri(uri).method(method).body(body)?;
+    *request.headers_mut() = headers;
     // create response channel
     let (tx, rx) = oneshot::channel::<ResultResponse>();
     // Perform request
-    let _ = channel
+    channel
         .send(Message {
-            request: request,
+            request,
             responder: tx,
         })
-        .await;
-    let response = rx.await.unwrap().unwrap();
-    let status = response.status().clone();
+        .await
+        .map_err(|_| SirixError::ChannelClosed)?;
+    let response = rx.await.map_err(|_| SirixError::ChannelClosed)??;
+    check_status(response).await
+}
+
+pub async fn request_impl<T: DeserializeOwned>(
+    channel: Sender<Message>,
+    scheme: Scheme,
+    authority: Authority,
+    path_and_query: PathAndQuery,
+    method: Method,
+    headers: HeaderMap,
+    body: Body,
+) -> SirixResult<SirixResponse<T>> {
+    let response = send_request(
+        channel,
+        scheme,
+        authority,
+        path_and_query,
+        method,
+        headers,
+        body,
+    )
+    .await?;
+    let status = response.status();
     let headers = response.headers().clone();
     // Aggregate body
     let body = body::aggregate(response).await?;
@@ -87,14 +193,45 @@ pub async fn request_impl<T: DeserializeOwned>(
 
     match parsed_json_response {
         Ok(parsed) => Ok(SirixResponse {
-            headers: headers.to_owned(),
-            status: status,
+            headers,
+            status,
             body: parsed,
         }),
         Err(err) => Err(SirixError::FormatError(err)),
     }
 }
 
+pub async fn request_impl_string(
+    channel: Sender<Message>,
+    scheme: Scheme,
+    authority: Authority,
+    path_and_query: PathAndQuery,
+    method: Method,
+    headers: HeaderMap,
+    body: Body,
+) -> SirixResult<SirixResponse<String>> {
+    let response = send_request(
+        channel,
+        scheme,
+        authority,
+        path_and_query,
+        method,
+        headers,
+        body,
+    )
+    .await?;
+    let status = response.status();
+    let headers = response.headers().clone();
+    // Aggregate body
+    let body = body::to_bytes(response).await?;
+
+    Ok(SirixResponse {
+        headers,
+        status,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
 pub async fn request_impl_fire_no_response(
     channel: Sender<Message>,
     scheme: Scheme,
@@ -103,34 +240,348 @@ pub async fn request_impl_fire_no_response(
     method: Method,
     headers: HeaderMap,
     body: Body,
-) -> SirixResponse<()> {
-    let uri = Uri::builder()
-        .scheme(scheme)
-        .authority(authority)
-        .path_and_query(path_and_query)
-        .build()
-        .unwrap();
-    // create request
-    let mut request_builder = Request::builder().uri(uri).method(method);
-    for header in headers {
-        request_builder = request_builder.header(header.0.unwrap(), header.1);
+) -> SirixResult<SirixResponse<()>> {
+    let response = send_request(
+        channel,
+        scheme,
+        authority,
+        path_and_query,
+        method,
+        headers,
+        body,
+    )
+    .await?;
+    Ok(SirixResponse {
+        headers: response.headers().clone(),
+        status: response.status(),
+        body: (),
+    })
+}
+
+/// Sends requests to the SirixDB server at `scheme://authority` through the client task
+#[derive(Debug, Clone)]
+pub struct HyperTransport {
+    scheme: Scheme,
+    authority: Authority,
+    channel: Sender<Message>,
+    /// the tasks serving `channel`, if a builder started them; they stop once the
+    /// last transport sharing them is dropped
+    tasks: Option<Arc<BackgroundTasks>>,
+}
+
+impl HyperTransport {
+    pub fn new(scheme: Scheme, authority: Authority, channel: Sender<Message>) -> Self {
+        Self {
+            scheme,
+            authority,
+            channel,
+            tasks: None,
+        }
     }
-    let request = request_builder.body(body).unwrap();
-    // create response channel
-    let (tx, rx) = oneshot::channel::<ResultResponse>();
-    // Perform request
-    let _ = channel
-        .send(Message {
-            request: request,
-            responder: tx,
+
+    /// Keep `tasks` running for as long as this transport, or a clone of it, exists
+    pub(crate) fn with_tasks(mut self, tasks: Arc<BackgroundTasks>) -> Self {
+        self.tasks = Some(tasks);
+        self
+    }
+
+    pub fn scheme(&self) -> &Scheme {
+        &self.scheme
+    }
+
+    pub fn authority(&self) -> &Authority {
+        &self.authority
+    }
+
+    pub fn channel(&self) -> &Sender<Message> {
+        &self.channel
+    }
+}
+
+/// The path, method, headers and body of `request` for hyper
+fn prepare(request: SirixRequest) -> SirixResult<(PathAndQuery, Method, HeaderMap, Body)> {
+    let path_and_query = PathAndQuery::from_str(&request.path_and_query())?;
+    let method = match request.method {
+        transport::Method::Get => Method::GET,
+        transport::Method::Head => Method::HEAD,
+        transport::Method::Post => Method::POST,
+        transport::Method::Put => Method::PUT,
+        transport::Method::Delete => Method::DELETE,
+    };
+    let mut headers = HeaderMap::new();
+    for (name, value) in &request.headers {
+        headers.append(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+    }
+    let body = match request.body {
+        Some(body) => Body::from(body),
+        None => Body::empty(),
+    };
+    Ok((path_and_query, method, headers, body))
+}
+
+impl Transport for HyperTransport {
+    type Output<T> = ResponseFuture<T>;
+    type Stream<'a> = Body;
+
+    fn send_json<T: DeserializeOwned + Send + 'static>(
+        &self,
+        request: SirixRequest,
+    ) -> ResponseFuture<T> {
+        let transport = self.clone();
+        Box::pin(async move {
+            let (path_and_query, method, headers, body) = prepare(request)?;
+            request_impl(
+                transport.channel,
+                transport.scheme,
+                transport.authority,
+                path_and_query,
+                method,
+                headers,
+                body,
+            )
+            .await
+        })
+    }
+
+    fn send_string(&self, request: SirixRequest) -> ResponseFuture<String> {
+        let transport = self.clone();
+        Box::pin(async move {
+            let (path_and_query, method, headers, body) = prepare(request)?;
+            request_impl_string(
+                transport.channel,
+                transport.scheme,
+                transport.authority,
+                path_and_query,
+                method,
+                headers,
+                body,
+            )
+            .await
+        })
+    }
+
+    fn send_no_content(&self, request: SirixRequest) -> ResponseFuture<()> {
+        let transport = self.clone();
+        Box::pin(async move {
+            let (path_and_query, method, headers, body) = prepare(request)?;
+            request_impl_fire_no_response(
+                transport.channel,
+                transport.scheme,
+                transport.authority,
+                path_and_query,
+                method,
+                headers,
+                body,
+            )
+            .await
+        })
+    }
+
+    fn send_stream<'a>(&self, request: SirixRequest, body: Body) -> ResponseFuture<()> {
+        let transport = self.clone();
+        Box::pin(async move {
+            let (path_and_query, method, headers, _) = prepare(request)?;
+            request_impl_fire_no_response(
+                transport.channel,
+                transport.scheme,
+                transport.authority,
+                path_and_query,
+                method,
+                headers,
+                body,
+            )
+            .await
         })
+    }
+}
+
+/// Turn unsuccessful responses into the matching `SirixError`.
+async fn check_status(response: Response<Body>) -> SirixResult<Response<Body>> {
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error()) {
+        return Ok(response);
+    }
+    let etag = response
+        .headers()
+        .get("etag")
+        .and_then(|etag| etag.to_str().ok())
+        .map(String::from);
+    let body = body::to_bytes(response).await?;
+    Err(SirixError::from_status(
+        status,
+        etag,
+        String::from_utf8_lossy(&body).into_owned(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::http::uri::Uri;
+    use mockito::mock;
+    use std::str::FromStr;
+
+    async fn send(
+        method: Method,
+        path: &'static str,
+    ) -> SirixResult<SirixResponse<serde_json::Value>> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let parsed = mockito::server_url().parse::<Uri>().unwrap();
+        request_impl(
+            sender,
+            parsed.scheme().unwrap().to_owned(),
+            parsed.authority().unwrap().to_owned(),
+            PathAndQuery::from_str(path).unwrap(),
+            method,
+            HeaderMap::new(),
+            Body::empty(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_not_found() {
+        let _m = mock("GET", "/missing").with_status(404).create();
+        let response = send(Method::GET, "/missing").await;
+        assert!(matches!(response, Err(SirixError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_precondition_failed() {
+        let _m = mock("POST", "/database/resource")
+            .with_status(412)
+            .with_header("etag", "current")
+            .create();
+        let response = send(Method::POST, "/database/resource").await;
+        match response {
+            Err(SirixError::PreconditionFailed { etag }) => {
+                assert_eq!(etag.as_deref(), Some("current"))
+            }
+            other => panic!("expected PreconditionFailed, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_error() {
+        let _m = mock("DELETE", "/database")
+            .with_status(500)
+            .with_body("boom")
+            .create();
+        let response = send(Method::DELETE, "/database").await;
+        match response {
+            Err(SirixError::ServerError { status, body }) => {
+                assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(body, "boom");
+            }
+            other => panic!("expected ServerError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_error() {
+        let _m = mock("PUT", "/database/unsupported-async")
+            .with_status(415)
+            .with_body("unsupported media type")
+            .create();
+        let response = send(Method::PUT, "/database/unsupported-async").await;
+        match response {
+            Err(SirixError::ClientError { status, body }) => {
+                assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+                assert_eq!(body, "unsupported media type");
+            }
+            other => panic!("expected ClientError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conflict() {
+        let _m = mock("PUT", "/conflict-async")
+            .with_status(409)
+            .with_body("resource is locked")
+            .create();
+        let response = send(Method::PUT, "/conflict-async").await;
+        match response {
+            Err(SirixError::Conflict { body }) => assert_eq!(body, "resource is locked"),
+            other => panic!("expected Conflict, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_channel_closed() {
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        drop(receiver);
+        let response = request_impl_string(
+            sender,
+            Scheme::HTTP,
+            Authority::from_static("localhost:9443"),
+            PathAndQuery::from_static("/"),
+            Method::GET,
+            HeaderMap::new(),
+            Body::empty(),
+        )
         .await;
-    let response = rx.await.unwrap().unwrap();
-    let status = response.status().clone();
-    let headers = response.headers().clone();
-    SirixResponse {
-        headers: headers.to_owned(),
-        status: status,
-        body: (),
+        assert!(matches!(response, Err(SirixError::ChannelClosed)));
+    }
+
+    #[tokio::test]
+    async fn test_streamed_upload() {
+        let _m = mock("POST", "/upload-async").with_status(200).create();
+        let _m2 = mock("GET", "/during-upload-async")
+            .with_status(200)
+            .create();
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client_with_options(
+            hyper::Client::new(),
+            receiver,
+            ClientOptions {
+                timeout: Some(Duration::from_millis(50)),
+                ..ClientOptions::default()
+            },
+        );
+        let parsed = mockito::server_url().parse::<Uri>().unwrap();
+        let send = |method, path, body| {
+            send_request(
+                sender.clone(),
+                parsed.scheme().unwrap().to_owned(),
+                parsed.authority().unwrap().to_owned(),
+                PathAndQuery::from_static(path),
+                method,
+                HeaderMap::new(),
+                body,
+            )
+        };
+        let (mut chunks, body) = Body::channel();
+        let upload = tokio::spawn(send(Method::POST, "/upload-async", body));
+        // other requests are sent while the upload is in progress
+        let response = send(Method::GET, "/during-upload-async", Body::empty()).await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+        // the upload takes longer than the timeout
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            chunks.send_data("chunk".into()).await.unwrap();
+        }
+        drop(chunks);
+        assert_eq!(upload.await.unwrap().unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_connection_refused() {
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let response = request_impl_string(
+            sender,
+            Scheme::HTTP,
+            Authority::from_static("127.0.0.1:1"),
+            PathAndQuery::from_static("/"),
+            Method::GET,
+            HeaderMap::new(),
+            Body::empty(),
+        )
+        .await;
+        assert!(matches!(response, Err(SirixError::ConnectionError(_))));
     }
 }
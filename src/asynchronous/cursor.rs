@@ -0,0 +1,187 @@
+//! Paging through the children of a node
+
+use super::super::types::{
+    HistoryQuery, Json, MetaNode, MetadataType, ReadArgs, RevisionArg, SingleRevision,
+};
+use super::resource::Resource;
+use super::SirixResult;
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A page of children, and the revision it was read from
+type PageFuture =
+    Pin<Box<dyn Future<Output = SirixResult<(MetaNode, Option<SingleRevision>)>> + Send>>;
+
+/// Streams the children of an array or object, fetching `page_size` children per request.
+///
+/// Children are read with their node keys, and each page continues after
+/// the last node of the previous one, so only a single page is held in memory at a time.
+/// Without a `revision`, every page is read from the latest revision when the first page is.
+/// The children of an object are its `MetaNode::ObjectKey`s.
+pub struct ChildrenCursor {
+    resource: Resource<Json>,
+    node_id: Option<u128>,
+    page_size: u64,
+    revision: Option<SingleRevision>,
+    last_node_key: Option<u64>,
+    buffer: VecDeque<MetaNode>,
+    pending: Option<PageFuture>,
+    done: bool,
+}
+
+impl ChildrenCursor {
+    pub fn new(
+        resource: Resource<Json>,
+        node_id: Option<u128>,
+        page_size: u64,
+        revision: Option<SingleRevision>,
+    ) -> Self {
+        Self {
+            resource,
+            node_id,
+            page_size: page_size.max(1),
+            revision,
+            last_node_key: None,
+            buffer: VecDeque::new(),
+            pending: None,
+            done: false,
+        }
+    }
+
+    fn fetch_page(&self) -> PageFuture {
+        let resource = self.resource.clone();
+        let first_page = self.last_node_key.is_none();
+        let mut revision = self.revision.clone();
+        let mut read_args = ReadArgs {
+            node_id: self.node_id,
+            max_level: Some(1),
+            top_level_limit: Some(self.page_size),
+            top_level_skip_last_node: self.last_node_key,
+            ..ReadArgs::default()
+        };
+        Box::pin(async move {
+            if revision.is_none() && first_page {
+                revision = latest_revision(&resource).await?;
+            }
+            read_args.revision = revision.clone().map(RevisionArg::SingleRevision);
+            let response = resource
+                .read_with_metadata(MetadataType::Key, read_args)
+                .await?;
+            Ok((response.body, revision))
+        })
+    }
+
+    fn add_page(&mut self, node: MetaNode, revision: Option<SingleRevision>) {
+        self.revision = revision;
+        let children = node.into_children();
+        match children.last() {
+            Some(last) if children.len() as u64 >= self.page_size => {
+                self.last_node_key = Some(last.node_key() as u64);
+            }
+            _ => self.done = true,
+        }
+        self.buffer.extend(children);
+    }
+}
+
+/// The latest revision of `resource`, if it has any
+async fn latest_revision(resource: &Resource<Json>) -> SirixResult<Option<SingleRevision>> {
+    let query = HistoryQuery {
+        range: None,
+        limit: Some(1),
+    };
+    let history = resource.history_with(query).await?.body;
+    Ok(history
+        .last()
+        .map(|commit| SingleRevision::Number(commit.revision as u64)))
+}
+
+impl Stream for ChildrenCursor {
+    type Item = SirixResult<MetaNode>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(child) = this.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(child)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if this.pending.is_none() {
+                this.pending = Some(this.fetch_page());
+            }
+            let page = match this
+                .pending
+                .as_mut()
+                .map(|pending| pending.as_mut().poll(cx))
+            {
+                Some(Poll::Ready(page)) => page,
+                _ => return Poll::Pending,
+            };
+            this.pending = None;
+            match page {
+                Ok((node, revision)) => this.add_page(node, revision),
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::*;
+    use mockito::mock;
+    use std::future::poll_fn;
+
+    #[tokio::test]
+    async fn test_children_cursor() {
+        let _history = mock("GET", "/database/cursor-async/history?revisions=1")
+            .with_status(200)
+            .with_body(
+                r#"{"history":[{"revisionTimestamp":"2019-05-07T00:00:00","revision":7,"author":"admin","commitMessage":""}]}"#,
+            )
+            .expect(1)
+            .create();
+        // every page is read from the revision that was latest for the first one
+        let _m = mock(
+            "GET",
+            "/database/cursor-async?maxLevel=1&nextTopLevelNodes=2&revision=7&withMetadata=nodeKey",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"metadata":{"nodeKey":1},"value":[
+                {"key":"foo","metadata":{"nodeKey":2},"value":{"metadata":{"nodeKey":3},"value":1}},
+                {"key":"bar","metadata":{"nodeKey":4},"value":{"metadata":{"nodeKey":5},"value":2}}
+            ]}"#,
+        )
+        .create();
+        let _m2 = mock(
+            "GET",
+            "/database/cursor-async?maxLevel=1&nextTopLevelNodes=2&lastTopLevelNodeKey=4&revision=7&withMetadata=nodeKey",
+        )
+        .with_status(200)
+        .with_body(r#"{"metadata":{"nodeKey":1},"value":{}}"#)
+        .expect(1)
+        .create();
+        let resource = test_mocks::async_json_resource("cursor-async");
+        let mut cursor = resource.children_cursor(None, 2, None);
+        let mut keys = Vec::new();
+        while let Some(child) = poll_fn(|cx| Pin::new(&mut cursor).poll_next(cx)).await {
+            match child.unwrap() {
+                MetaNode::ObjectKey(member) => keys.push(member.key),
+                child => panic!("expected an object key, got {:?}", child),
+            }
+        }
+        assert_eq!(keys, vec!["foo", "bar"]);
+        _m2.assert();
+        _history.assert();
+    }
+}
@@ -1,9 +1,11 @@
 //! Working with a Sirix database.
 
+use super::super::endpoints;
 use super::super::info::TokenData;
+use super::super::transport::Transport;
 use super::super::types::{DbInfo, DbType, Json, Xml};
-use super::client::{Message, SirixResponse};
-use super::http::{create_database, delete_database, get_database_info};
+use super::auth::authorization;
+use super::client::{HyperTransport, Message, SirixResponse};
 use super::resource::Resource;
 use super::SirixResult;
 use hyper::http::uri::{Authority, Scheme};
@@ -18,98 +20,38 @@ pub struct Database<T> {
     pub db_name: String,
     /// The type of that database.
     pub db_type: DbType,
-    /// the scheme with which to access the SirixDB server
-    scheme: Scheme,
-    /// the authority at which to access the SirixDB server
-    authority: Authority,
-    /// the message channel for sending HTTP requests
-    channel: Sender<Message>,
+    /// sends requests to the SirixDB server
+    transport: HyperTransport,
     /// the channel containing authentication data
     auth_channel: Option<Receiver<Option<TokenData>>>,
 }
 
 impl<T> Database<T> {
     pub async fn info(&self) -> SirixResult<SirixResponse<DbInfo>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                get_database_info(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                get_database_info(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+        self.transport
+            .send_json(
+                endpoints::database_info(&self.db_name)?
+                    .authorization(authorization(&self.auth_channel)?),
+            )
+            .await
     }
 
     pub async fn delete(&self) -> SirixResult<SirixResponse<()>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                delete_database(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                delete_database(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+        self.transport
+            .send_no_content(
+                endpoints::delete_database(&self.db_name)?
+                    .authorization(authorization(&self.auth_channel)?),
+            )
+            .await
     }
 
     pub async fn create(&self) -> SirixResult<SirixResponse<()>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                create_database(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                create_database(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+        self.transport
+            .send_no_content(
+                endpoints::create_database(&self.db_name, &self.db_type)?
+                    .authorization(authorization(&self.auth_channel)?),
+            )
+            .await
     }
 }
 
@@ -120,14 +62,24 @@ impl Database<Json> {
         authority: Authority,
         channel: Sender<Message>,
         auth_channel: Option<Receiver<Option<TokenData>>>,
+    ) -> Self {
+        Self::with_transport(
+            db_name,
+            HyperTransport::new(scheme, authority, channel),
+            auth_channel,
+        )
+    }
+
+    pub(crate) fn with_transport(
+        db_name: String,
+        transport: HyperTransport,
+        auth_channel: Option<Receiver<Option<TokenData>>>,
     ) -> Self {
         Self {
             _t: Json,
             db_name,
             db_type: DbType::Json(Json),
-            scheme,
-            authority,
-            channel,
+            transport,
             auth_channel,
         }
     }
@@ -135,12 +87,10 @@ impl Database<Json> {
     /// a helper function to create a Resource struct corresponding
     /// to a resource within the database this Database struct represents
     pub fn resource(&self, name: String) -> Resource<Json> {
-        Resource::<Json>::new(
+        Resource::<Json>::with_transport(
             self.db_name.clone(),
             name,
-            self.scheme.clone(),
-            self.authority.clone(),
-            self.channel.clone(),
+            self.transport.clone(),
             self.auth_channel.clone(),
         )
     }
@@ -153,14 +103,24 @@ impl Database<Xml> {
         authority: Authority,
         channel: Sender<Message>,
         auth_channel: Option<Receiver<Option<TokenData>>>,
+    ) -> Self {
+        Self::with_transport(
+            db_name,
+            HyperTransport::new(scheme, authority, channel),
+            auth_channel,
+        )
+    }
+
+    pub(crate) fn with_transport(
+        db_name: String,
+        transport: HyperTransport,
+        auth_channel: Option<Receiver<Option<TokenData>>>,
     ) -> Self {
         Self {
             _t: Xml,
             db_name,
             db_type: DbType::XML(Xml),
-            scheme,
-            authority,
-            channel,
+            transport,
             auth_channel,
         }
     }
@@ -168,12 +128,10 @@ impl Database<Xml> {
     /// a helper function to create a Resource struct corresponding
     /// to a resource within the database this Database struct represents
     pub fn resource(&self, name: String) -> Resource<Xml> {
-        Resource::<Xml>::new(
+        Resource::<Xml>::with_transport(
             self.db_name.clone(),
             name,
-            self.scheme.clone(),
-            self.authority.clone(),
-            self.channel.clone(),
+            self.transport.clone(),
             self.auth_channel.clone(),
         )
     }
@@ -1,6 +1,11 @@
 //! Error type
 
+use super::super::endpoints::InvalidName;
+use super::super::json::JsonError;
+use super::super::types::{InvalidPath, UnexpectedQueryResult};
+use super::super::xml::XmlError;
 use hyper::http;
+use hyper::StatusCode;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -17,6 +22,73 @@ pub enum SirixError {
     // #[error("Invalid URI")]
     #[error(transparent)]
     InvalidUri(#[from] http::uri::InvalidUri),
+    #[error(transparent)]
+    InvalidHeader(#[from] http::header::InvalidHeaderValue),
+    #[error(transparent)]
+    InvalidHeaderName(#[from] http::header::InvalidHeaderName),
+    #[error("base URI must include a scheme and an authority: {0}")]
+    IncompleteUri(String),
+    #[error("the HTTP client task is no longer running")]
+    ChannelClosed,
+    #[error("timed out waiting for a response from SirixDB")]
+    Timeout,
+    #[cfg(feature = "rustls")]
+    #[error(transparent)]
+    Tls(#[from] rustls::Error),
+    #[cfg(feature = "rustls")]
+    #[error("invalid TLS certificate or key: {0}")]
+    InvalidCertificate(String),
+    /// a typed resource that did not match, with the path to the failing field
+    #[error(transparent)]
+    JsonError(#[from] JsonError),
+    #[error(transparent)]
+    InvalidName(#[from] InvalidName),
+    #[error(transparent)]
+    InvalidPath(#[from] InvalidPath),
+    #[error(transparent)]
+    UnexpectedQueryResult(#[from] UnexpectedQueryResult),
+    #[error(transparent)]
+    XmlError(Box<XmlError>),
+    #[error("no authentication token is available")]
+    NotAuthenticated,
+    #[error("SirixDB did not return an ETag for the requested node")]
+    MissingEtag,
+    #[error("the requested database, resource or node does not exist")]
+    NotFound,
+    #[error("not authenticated with SirixDB")]
+    Unauthorized,
+    #[error("not permitted to perform this operation")]
+    Forbidden,
+    #[error("the given ETag does not match the current state of the node")]
+    PreconditionFailed { etag: Option<String> },
+    #[error("the request conflicts with the current state of the resource: {body}")]
+    Conflict { body: String },
+    /// any other 4xx status, such as a malformed request
+    #[error("SirixDB rejected the request with status {status}: {body}")]
+    ClientError { status: StatusCode, body: String },
+    #[error("SirixDB responded with status {status}: {body}")]
+    ServerError { status: StatusCode, body: String },
+}
+
+impl SirixError {
+    /// Map an unsuccessful HTTP response to the matching error.
+    pub fn from_status(status: StatusCode, etag: Option<String>, body: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => SirixError::Unauthorized,
+            StatusCode::FORBIDDEN => SirixError::Forbidden,
+            StatusCode::NOT_FOUND => SirixError::NotFound,
+            StatusCode::CONFLICT => SirixError::Conflict { body },
+            StatusCode::PRECONDITION_FAILED => SirixError::PreconditionFailed { etag },
+            _ if status.is_client_error() => SirixError::ClientError { status, body },
+            _ => SirixError::ServerError { status, body },
+        }
+    }
+}
+
+impl From<XmlError> for SirixError {
+    fn from(err: XmlError) -> Self {
+        SirixError::XmlError(Box::new(err))
+    }
 }
 
 pub type SirixResult<T> = std::result::Result<T, SirixError>;
@@ -0,0 +1,176 @@
+//! Paging through the history of a resource
+
+use super::super::timestamp::Timestamp;
+use super::super::types::{Commit, History, HistoryQuery, TwoRevisions};
+use super::super::utils::{
+    history_in_range, history_next_newest, history_page_query, history_reaches,
+};
+use super::client::SirixResponse;
+use super::resource::Resource;
+use super::SirixResult;
+use futures_core::Stream;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+type PageFuture = Pin<Box<dyn Future<Output = SirixResult<SirixResponse<History>>> + Send>>;
+
+/// Streams the commits of a resource, newest first, fetching `page_size` commits per request.
+///
+/// Pages are requested lazily, so only a single page is held in memory at a time.
+/// Pages are requested by revision number, since `/history` cannot select commits
+/// by timestamp. A timestamp range is applied to each page instead, and paging
+/// stops at the first commit before it.
+pub struct HistoryPager<T> {
+    resource: Resource<T>,
+    range: Option<TwoRevisions>,
+    page_size: u64,
+    remaining: Option<u64>,
+    /// the newest revision of the next page, unknown until the first page of an open range
+    newest: Option<u64>,
+    /// the oldest revision of the range
+    oldest: u64,
+    /// the start of a timestamp range
+    since: Option<Timestamp>,
+    buffer: VecDeque<Commit>,
+    pending: Option<(PageFuture, HistoryQuery)>,
+    done: bool,
+}
+
+impl<T: Clone + Send + Sync + Unpin + 'static> HistoryPager<T> {
+    pub fn new(resource: Resource<T>, query: HistoryQuery, page_size: u64) -> Self {
+        let (newest, oldest) = match &query.range {
+            Some(TwoRevisions::Number(start, end)) => (Some(*end), *start),
+            _ => (None, 0),
+        };
+        let since = match &query.range {
+            Some(TwoRevisions::Timestamp(start, _)) => Some(start),
+            _ => None,
+        }
+        .cloned();
+        Self {
+            resource,
+            page_size: page_size.max(1),
+            remaining: query.limit,
+            newest,
+            oldest,
+            since,
+            buffer: VecDeque::new(),
+            pending: None,
+            done: query.limit == Some(0) || matches!(newest, Some(newest) if newest < oldest),
+            range: query.range,
+        }
+    }
+
+    /// The request for the next page, and the query it was sent with
+    fn fetch_page(&self) -> (PageFuture, HistoryQuery) {
+        let resource = self.resource.clone();
+        // commits outside a timestamp range are dropped, so they don't count towards the limit
+        let size = match (&self.range, self.remaining) {
+            (Some(TwoRevisions::Timestamp(..)), _) | (_, None) => self.page_size,
+            (_, Some(remaining)) => remaining.min(self.page_size),
+        };
+        let query = history_page_query(self.newest, self.oldest, size);
+        let request = query.clone();
+        (
+            Box::pin(async move { resource.history_with(request).await }),
+            query,
+        )
+    }
+
+    fn add_page(&mut self, history: History, query: &HistoryQuery) {
+        self.newest = history_next_newest(query, &history, self.oldest);
+        let reached = history_reaches(&history, self.since.as_ref());
+        let mut commits = history_in_range(history, self.range.as_ref());
+        if let Some(remaining) = self.remaining.as_mut() {
+            commits.truncate(*remaining as usize);
+            *remaining -= commits.len() as u64;
+        }
+        self.done = self.newest.is_none() || reached || self.remaining == Some(0);
+        self.buffer.extend(commits);
+    }
+}
+
+impl<T: Clone + Send + Sync + Unpin + 'static> Stream for HistoryPager<T> {
+    type Item = SirixResult<Commit>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(commit) = this.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(commit)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if this.pending.is_none() {
+                this.pending = Some(this.fetch_page());
+            }
+            let page = match this
+                .pending
+                .as_mut()
+                .map(|(pending, _)| pending.as_mut().poll(cx))
+            {
+                Some(Poll::Ready(page)) => page,
+                _ => return Poll::Pending,
+            };
+            let (_, query) = this.pending.take().unwrap();
+            match page {
+                Ok(response) => this.add_page(response.body, &query),
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::*;
+    use mockito::mock;
+    use std::future::poll_fn;
+
+    #[tokio::test]
+    async fn test_history_pager() {
+        let _m = mock(
+            "GET",
+            "/database/paged-async/history?startRevision=8&endRevision=9",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"history":[
+                {"revisionTimestamp":"2019-05-09T00:00:00","revision":9,"author":"admin","commitMessage":"nine"},
+                {"revisionTimestamp":"2019-05-08T00:00:00","revision":8,"author":"admin","commitMessage":"eight"}
+            ]}"#,
+        )
+        .create();
+        let _m2 = mock(
+            "GET",
+            "/database/paged-async/history?startRevision=7&endRevision=7",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"history":[{"revisionTimestamp":"2019-05-07T00:00:00","revision":7,"author":"admin","commitMessage":"seven"}]}"#,
+        )
+        .expect(1)
+        .create();
+        let resource = test_mocks::async_json_resource("paged-async");
+        let mut pager = resource.history_pager(
+            HistoryQuery {
+                range: Some(TwoRevisions::Number(2, 9)),
+                limit: Some(3),
+            },
+            2,
+        );
+        let mut messages = Vec::new();
+        while let Some(commit) = poll_fn(|cx| Pin::new(&mut pager).poll_next(cx)).await {
+            messages.push(commit.unwrap().commit_message);
+        }
+        assert_eq!(messages, vec!["nine", "eight", "seven"]);
+        _m2.assert();
+    }
+}
@@ -1,10 +1,17 @@
 pub mod auth;
+pub mod builder;
 pub mod client;
+pub mod cursor;
 pub mod database;
 pub mod error;
-pub mod http;
+pub mod history;
+pub mod query;
 pub mod resource;
+pub mod revision;
 pub mod sirix;
+#[cfg(feature = "rustls")]
+pub mod tls;
+pub mod upload;
 
 pub use error::SirixResult;
 
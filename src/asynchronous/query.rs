@@ -0,0 +1,136 @@
+//! Paging through the result sequence of a query
+
+use super::super::types::Query;
+use super::super::utils::query_result_items;
+use super::client::SirixResponse;
+use super::sirix::Sirix;
+use super::SirixResult;
+use futures_core::Stream;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+type PageFuture = Pin<Box<dyn Future<Output = SirixResult<SirixResponse<Value>>> + Send>>;
+
+/// Streams the items of a query result, fetching `page_size` items per request.
+///
+/// Pages are requested lazily, so only a single page is held in memory at a time.
+pub struct QueryPager {
+    sirix: Sirix,
+    query: String,
+    page_size: u128,
+    next_index: u128,
+    buffer: VecDeque<Value>,
+    pending: Option<PageFuture>,
+    done: bool,
+}
+
+impl QueryPager {
+    pub fn new(sirix: Sirix, query: &str, page_size: u128) -> Self {
+        Self {
+            sirix,
+            query: query.to_owned(),
+            page_size: page_size.max(1),
+            next_index: 0,
+            buffer: VecDeque::new(),
+            pending: None,
+            done: false,
+        }
+    }
+
+    fn fetch_page(&self) -> PageFuture {
+        let sirix = self.sirix.clone();
+        let query = Query {
+            start_result_seq_index: Some(self.next_index),
+            end_result_seq_index: Some(self.next_index + self.page_size - 1),
+            query: self.query.clone(),
+        };
+        Box::pin(async move { sirix.query(query).await })
+    }
+}
+
+impl Stream for QueryPager {
+    type Item = SirixResult<Value>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(item) = this.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            if this.pending.is_none() {
+                this.pending = Some(this.fetch_page());
+            }
+            let page = match this
+                .pending
+                .as_mut()
+                .map(|pending| pending.as_mut().poll(cx))
+            {
+                Some(Poll::Ready(page)) => page,
+                _ => return Poll::Pending,
+            };
+            this.pending = None;
+            match page.and_then(|response| Ok(query_result_items(response.body)?)) {
+                Ok(items) => {
+                    if (items.len() as u128) < this.page_size {
+                        this.done = true;
+                    }
+                    this.next_index += this.page_size;
+                    this.buffer.extend(items);
+                }
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::client::spawn_client;
+    use super::*;
+    use hyper::http::uri::Uri;
+    use mockito::{mock, Matcher};
+    use std::future::poll_fn;
+
+    #[tokio::test]
+    async fn test_query_pager() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/")
+            .match_body(Matcher::Json(serde_json::json!({
+                "startResultSeqIndex": 0,
+                "endResultSeqIndex": 1,
+                "query": "1 to 3"
+            })))
+            .with_status(200)
+            .with_body(r#"{"rest":[1,2]}"#)
+            .create();
+        let _m2 = mock("POST", "/")
+            .match_body(Matcher::Json(serde_json::json!({
+                "startResultSeqIndex": 2,
+                "endResultSeqIndex": 3,
+                "query": "1 to 3"
+            })))
+            .with_status(200)
+            .with_body(r#"{"rest":[3]}"#)
+            .expect(1)
+            .create();
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let sirix = Sirix::new(url.parse::<Uri>().unwrap(), sender, None);
+        let mut pager = sirix.query_pager("1 to 3", 2);
+        let mut items = Vec::new();
+        while let Some(item) = poll_fn(|cx| Pin::new(&mut pager).poll_next(cx)).await {
+            items.push(item.unwrap());
+        }
+        assert_eq!(items, vec![1, 2, 3]);
+        _m2.assert();
+    }
+}
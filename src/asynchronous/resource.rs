@@ -1,19 +1,37 @@
 //! Working with a Sirix resource.
 
+use super::super::endpoints;
 use super::super::info::TokenData;
+use super::super::json;
+use super::super::transport::{SirixRequest, Transport};
 use super::super::types::{
-    DbType, DiffArgs, History, Json, MetaNode, MetadataType, NodeIdAndEtag, ReadArgs,
-    SingleRevision, Xml,
+    parse_path, Commit, DbType, DiffArgs, DiffResult, History, HistoryQuery, Insert, Json,
+    MetaNode, MetadataType, NodeIdAndEtag, PathSegment, Query, ReadArgs, RevisionArg,
+    SingleRevision, TwoRevisions, WriteOptions, Xml,
 };
-use super::super::utils::build_read_params;
-use super::client::{Message, SirixResponse};
-use super::http::{
-    create_resource, diff_resource, get_etag, read_resource, resource_delete, resource_exists,
-    resource_history,
+use super::super::utils::{
+    build_diff_params, build_history_params, build_query_params, build_read_params,
+    build_revision_params, build_write_params, commit_at, revision_history_query, select_history,
 };
+use super::super::xml::{self, parse_element, XmlMetaNode};
+use super::auth::authorization;
+use super::client::{HyperTransport, Message, SirixResponse};
+use super::cursor::ChildrenCursor;
+use super::error::SirixError;
+use super::history::HistoryPager;
+use super::revision::{RevisionRange, RevisionView};
+use super::upload::ProgressStream;
 use super::SirixResult;
+use bytes::Bytes;
+use futures_core::Stream;
 use hyper::http::uri::{Authority, Scheme};
+use hyper::{Body, HeaderMap, StatusCode};
+use minidom::Element;
 use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::io::AsyncRead;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::watch::Receiver;
 
@@ -27,12 +45,10 @@ pub struct Resource<T> {
     pub db_type: DbType,
     /// The name of the resource being accessed, or created if it does not yet exist.
     pub resource_name: String,
-    /// the scheme with which to access the SirixDB server
-    scheme: Scheme,
-    /// the authority at which to access the SirixDB server
-    authority: Authority,
-    /// the message channel for sending HTTP requests
-    channel: Sender<Message>,
+    /// recorded in the commit of each write, see `with_write_options`
+    write_options: WriteOptions,
+    /// sends requests to the SirixDB server
+    transport: HyperTransport,
     /// the channel containing authentication data
     auth_channel: Option<Receiver<Option<TokenData>>>,
 }
@@ -45,150 +61,221 @@ impl Resource<Json> {
         authority: Authority,
         channel: Sender<Message>,
         auth_channel: Option<Receiver<Option<TokenData>>>,
+    ) -> Self {
+        Self::with_transport(
+            db_name,
+            resource_name,
+            HyperTransport::new(scheme, authority, channel),
+            auth_channel,
+        )
+    }
+
+    pub(crate) fn with_transport(
+        db_name: String,
+        resource_name: String,
+        transport: HyperTransport,
+        auth_channel: Option<Receiver<Option<TokenData>>>,
     ) -> Self {
         Self {
             _t: Json,
-            db_name: db_name,
+            db_name,
             db_type: DbType::Json(Json),
-            resource_name: resource_name,
-            scheme: scheme,
-            authority: authority,
-            channel: channel,
-            auth_channel: auth_channel,
+            resource_name,
+            transport,
+            write_options: WriteOptions::default(),
+            auth_channel,
         }
     }
 
-    pub async fn history_raw<U: DeserializeOwned>(&self) -> SirixResult<SirixResponse<U>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                resource_history(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                resource_history(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+    pub async fn create_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        initial_data: String,
+    ) -> SirixResult<SirixResponse<U>> {
+        self.transport
+            .send_json(self.create_request()?.body(initial_data))
+            .await
     }
 
-    pub async fn history(&self) -> SirixResult<SirixResponse<History>> {
-        self.history_raw().await
+    pub async fn create(&self, initial_data: String) -> SirixResult<SirixResponse<Value>> {
+        self.create_raw(initial_data).await
     }
 
-    // TODO fix return type
-    pub async fn diff(&self, args: DiffArgs) -> SirixResult<SirixResponse<()>> {
-        let mut params: Vec<(String, String)> = Vec::new();
-        match args.node_id {
-            Some(node_id) => params.push(("startNodeKey".to_owned(), node_id.to_string())),
-            None => (),
-        }
-        match args.max_depth {
-            Some(max_depth) => params.push(("maxDepth".to_owned(), max_depth.to_string())),
-            None => (),
-        }
-        match args.first_revision {
-            SingleRevision::Timestamp(revision) => {
-                params.push(("first-revision".to_owned(), revision))
-            }
-            SingleRevision::Number(revision) => {
-                params.push(("first-revision".to_owned(), revision.to_string()))
-            }
-        }
-        match args.second_revision {
-            SingleRevision::Timestamp(revision) => {
-                params.push(("second-revision".to_owned(), revision))
-            }
-            SingleRevision::Number(revision) => {
-                params.push(("second-revision".to_owned(), revision.to_string()))
-            }
+    /// Create the resource with `value` serialized as JSON
+    pub async fn create_from<V: Serialize + ?Sized>(
+        &self,
+        value: &V,
+    ) -> SirixResult<SirixResponse<Value>> {
+        let data = json::serialize(value)?;
+        self.create(data).await
+    }
+
+    pub async fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Value>> {
+        self.read_raw(read_args).await
+    }
+
+    /// Read the resource into `U`.
+    ///
+    /// Unlike `read_raw`, errors report the path of the field that did not match.
+    pub async fn read_as<U: DeserializeOwned>(
+        &self,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.read_string(read_args).await?, json::deserialize)
+    }
+
+    pub async fn read_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<U>> {
+        let request = self.read_request(build_read_params(read_args))?;
+        self.transport.send_json(request).await
+    }
+
+    pub async fn read_with_metadata_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<U>> {
+        let mut params = build_read_params(read_args);
+        params.push(("withMetadata".to_owned(), meta_type.to_string()));
+        let request = self.read_request(params)?;
+        self.transport.send_json(request).await
+    }
+
+    pub async fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<MetaNode>> {
+        self.read_with_metadata_raw(meta_type, read_args).await
+    }
+
+    /// Lazily stream the children of the array or object with id `node_id`,
+    /// or of the top-level value, `page_size` children per request.
+    pub fn children_cursor(
+        &self,
+        node_id: Option<u128>,
+        page_size: u64,
+        revision: Option<SingleRevision>,
+    ) -> ChildrenCursor {
+        ChildrenCursor::new(self.clone(), node_id, page_size, revision)
+    }
+
+    /// Insert `data` relative to the node with id `node_id`, or replace that node.
+    ///
+    /// If no `etag` is given, the current ETag of the node is fetched first.
+    pub async fn update_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<U>> {
+        let request = self.update_request(node_id, insert, etag).await?.body(data);
+        self.transport.send_json(request).await
+    }
+
+    pub async fn update(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<Value>> {
+        self.update_raw(node_id, insert, data, etag).await
+    }
+
+    /// Like `update`, with `value` serialized as JSON
+    pub async fn update_from<V: Serialize + ?Sized>(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        value: &V,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<Value>> {
+        let data = json::serialize(value)?;
+        self.update(node_id, insert, data, etag).await
+    }
+
+    /// Find the node at `path` in the latest revision, and fetch its ETag.
+    ///
+    /// `path` is a JSON Pointer such as `/a/b/3/c`, or of the form `a.b[3].c`.
+    /// Returns `SirixError::NotFound` if there is no node at `path`.
+    pub async fn resolve(&self, path: &str) -> SirixResult<NodeIdAndEtag> {
+        self.resolve_with(path, MetaNode::at_path).await
+    }
+
+    /// Replace the node at `path` with `value`, see `resolve`.
+    pub async fn set<V: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        value: &V,
+    ) -> SirixResult<SirixResponse<Value>> {
+        let data = json::serialize(value)?;
+        let node = self.resolve(path).await?;
+        self.update(node.node_id, Insert::Replace, data, Some(node.etag))
+            .await
+    }
+
+    /// Delete the node at `path`, see `resolve`.
+    ///
+    /// For the member of an object, both the key and its value are deleted.
+    pub async fn remove(&self, path: &str) -> SirixResult<SirixResponse<()>> {
+        let node = self.resolve_with(path, MetaNode::member_at_path).await?;
+        self.delete(Some(node)).await
+    }
+
+    async fn resolve_with(
+        &self,
+        path: &str,
+        find: for<'a, 'b> fn(&'a MetaNode, &'b [PathSegment]) -> Option<&'a MetaNode>,
+    ) -> SirixResult<NodeIdAndEtag> {
+        let path = parse_path(path)?;
+        // only read as deep as the path goes, where object keys are a level
+        // between an object and the values of its members
+        let read_args = ReadArgs {
+            max_level: Some(2 * path.len() as u64 + 1),
+            ..ReadArgs::default()
         };
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                diff_resource(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    &self.resource_name,
-                    params,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                diff_resource(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    &self.resource_name,
-                    params,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+        let root = self
+            .read_with_metadata(MetadataType::Key, read_args)
+            .await?
+            .body;
+        let node_id = find(&root, &path).ok_or(SirixError::NotFound)?.node_key() as u128;
+        let etag = self.etag(node_id).await?.body;
+        Ok(NodeIdAndEtag { node_id, etag })
     }
 
-    pub async fn delete(
+    /// Run a JSONiq query with this resource as its context item.
+    ///
+    /// Accepts a query string, or a `Query` to fetch only part of the result sequence.
+    pub async fn query(
         &self,
-        node_and_etag: Option<NodeIdAndEtag>,
-    ) -> SirixResult<SirixResponse<()>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                resource_delete(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    node_and_etag,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                resource_delete(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    node_and_etag,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<Value>> {
+        self.query_raw(query, revision).await
     }
 
-    // TODO
-    // update
-    // query
+    pub async fn query_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<U>> {
+        let request = self.read_request(build_query_params(query.into(), revision))?;
+        self.transport.send_json(request).await
+    }
+
+    /// Run a query, and deserialize its result into `U`.
+    ///
+    /// Errors report the path of the field that did not match.
+    pub async fn query_as<U: DeserializeOwned>(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.query_string(query, revision).await?, json::deserialize)
+    }
 }
 
 impl Resource<Xml> {
@@ -199,199 +286,847 @@ impl Resource<Xml> {
         authority: Authority,
         channel: Sender<Message>,
         auth_channel: Option<Receiver<Option<TokenData>>>,
+    ) -> Self {
+        Self::with_transport(
+            db_name,
+            resource_name,
+            HyperTransport::new(scheme, authority, channel),
+            auth_channel,
+        )
+    }
+
+    pub(crate) fn with_transport(
+        db_name: String,
+        resource_name: String,
+        transport: HyperTransport,
+        auth_channel: Option<Receiver<Option<TokenData>>>,
     ) -> Self {
         Self {
             _t: Xml,
             db_name,
             db_type: DbType::XML(Xml),
             resource_name,
-            scheme,
-            authority,
-            channel,
+            transport,
+            write_options: WriteOptions::default(),
             auth_channel,
         }
     }
+
+    pub async fn create(&self, initial_data: String) -> SirixResult<SirixResponse<Element>> {
+        parse_body(self.create_string(initial_data).await?, parse_element)
+    }
+
+    /// Create the resource, and deserialize the response with quick-xml
+    pub async fn create_raw<U: DeserializeOwned>(
+        &self,
+        initial_data: String,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.create_string(initial_data).await?, xml::deserialize)
+    }
+
+    pub async fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Element>> {
+        parse_body(self.read_string(read_args).await?, parse_element)
+    }
+
+    /// Read the resource, and deserialize the response with quick-xml
+    pub async fn read_raw<U: DeserializeOwned>(
+        &self,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.read_string(read_args).await?, xml::deserialize)
+    }
+
+    /// Read the resource with the `rest:` attributes SirixDB adds for `meta_type`.
+    ///
+    /// Returns the outermost elements with metadata.
+    pub async fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<Vec<XmlMetaNode>>> {
+        parse_body(
+            self.read_with_metadata_string(meta_type, read_args).await?,
+            |body| XmlMetaNode::collect(&parse_element(body)?),
+        )
+    }
+
+    /// Run an XQuery with this resource as its context item.
+    ///
+    /// SirixDB wraps the result sequence in a `rest:sequence` element.
+    pub async fn query(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<Element>> {
+        parse_body(self.query_string(query, revision).await?, parse_element)
+    }
+
+    /// Run a query, and deserialize its result with quick-xml
+    pub async fn query_raw<U: DeserializeOwned>(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.query_string(query, revision).await?, xml::deserialize)
+    }
+
+    pub async fn update_string(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<String>> {
+        let request = self.update_request(node_id, insert, etag).await?.body(data);
+        self.transport.send_string(request).await
+    }
+
+    /// Insert `data` relative to the node with id `node_id`, or replace that node.
+    ///
+    /// If no `etag` is given, the current ETag of the node is fetched first.
+    pub async fn update(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<Element>> {
+        parse_body(
+            self.update_string(node_id, insert, data, etag).await?,
+            parse_element,
+        )
+    }
+
+    /// Update the resource, and deserialize the response with quick-xml
+    pub async fn update_raw<U: DeserializeOwned>(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(
+            self.update_string(node_id, insert, data, etag).await?,
+            xml::deserialize,
+        )
+    }
+}
+
+/// Parse the body of a response read as text
+pub(crate) fn parse_body<U, E: Into<SirixError>>(
+    response: SirixResponse<String>,
+    parse: impl FnOnce(&str) -> Result<U, E>,
+) -> SirixResult<SirixResponse<U>> {
+    Ok(SirixResponse {
+        body: parse(&response.body).map_err(Into::into)?,
+        status: response.status,
+        headers: response.headers,
+    })
 }
 
 impl<T> Resource<T> {
-    pub async fn create(&self, initial_data: String) -> SirixResult<SirixResponse<String>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                create_resource(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    initial_data,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                create_resource(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    initial_data,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+    pub async fn create_string(&self, initial_data: String) -> SirixResult<SirixResponse<String>> {
+        self.transport
+            .send_string(self.create_request()?.body(initial_data))
+            .await
     }
 
+    /// Create the resource with the initial data taken from `stream`,
+    /// without loading it into memory.
+    ///
+    /// `progress` is called with the number of bytes sent so far.
+    /// The resource SirixDB sends back is discarded rather than read into memory.
+    pub async fn create_from_stream(
+        &self,
+        stream: impl Stream<Item = Bytes> + Send + 'static,
+        progress: impl FnMut(u64) + Send + Unpin + 'static,
+    ) -> SirixResult<SirixResponse<()>> {
+        let body = Body::wrap_stream(ProgressStream::new(stream, progress));
+        let request = self.create_request()?;
+        self.transport.send_stream(request, body).await
+    }
+
+    /// Like `create_from_stream`, with the initial data read from `reader`
+    pub async fn create_from_async_reader(
+        &self,
+        reader: impl AsyncRead + Send + 'static,
+        progress: impl FnMut(u64) + Send + Unpin + 'static,
+    ) -> SirixResult<SirixResponse<()>> {
+        let body = Body::wrap_stream(ProgressStream::from_reader(reader, progress));
+        let request = self.create_request()?;
+        self.transport.send_stream(request, body).await
+    }
+
+    /// Whether the resource exists, from the status of a `HEAD` request
     pub async fn exists(&self) -> SirixResult<SirixResponse<bool>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                resource_exists(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                resource_exists(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
+        let response = self
+            .transport
+            .send_no_content(
+                endpoints::resource_exists(&self.db_name, &self.db_type, &self.resource_name)?
+                    .authorization(authorization(&self.auth_channel)?),
+            )
+            .await;
+        match response {
+            Ok(response) => Ok(SirixResponse {
+                status: response.status,
+                headers: response.headers,
+                body: true,
+            }),
+            Err(SirixError::NotFound) => Ok(SirixResponse {
+                status: StatusCode::NOT_FOUND,
+                headers: HeaderMap::new(),
+                body: false,
+            }),
+            Err(err) => Err(err),
         }
     }
 
+    /// The ETag of the node with the given id, as the body of the response
     pub async fn etag(&self, node_id: u128) -> SirixResult<SirixResponse<String>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                get_etag(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    node_id,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                get_etag(
-                    self.scheme.clone(),
-                    self.authority.clone(),
+        self.etag_at(node_id, None).await
+    }
+
+    /// The ETag of the node with the given id in `revision`, or in the latest revision
+    pub(crate) async fn etag_at(
+        &self,
+        node_id: u128,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<String>> {
+        let response = self
+            .transport
+            .send_no_content(
+                endpoints::get_etag(
                     &self.db_name,
-                    self.db_type.clone(),
+                    &self.db_type,
                     &self.resource_name,
                     node_id,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+                    build_revision_params(revision),
+                )?
+                .authorization(authorization(&self.auth_channel)?),
+            )
+            .await?;
+        let etag = match response.headers.get("etag") {
+            Some(etag) => etag
+                .to_str()
+                .map_err(|_| SirixError::MissingEtag)?
+                .to_owned(),
+            None => return Err(SirixError::MissingEtag),
+        };
+        Ok(SirixResponse {
+            status: response.status,
+            body: etag,
+            headers: response.headers,
+        })
     }
 
-    pub async fn read<U: DeserializeOwned>(
+    pub async fn read_string(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<String>> {
+        let request = self.read_request(build_read_params(read_args))?;
+        self.transport.send_string(request).await
+    }
+
+    pub async fn read_with_metadata_string(
         &self,
+        meta_type: MetadataType,
         read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<String>> {
+        let mut params = build_read_params(read_args);
+        params.push(("withMetadata".to_owned(), meta_type.to_string()));
+        let request = self.read_request(params)?;
+        self.transport.send_string(request).await
+    }
+
+    pub async fn query_string(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<String>> {
+        let request = self.read_request(build_query_params(query.into(), revision))?;
+        self.transport.send_string(request).await
+    }
+
+    pub async fn history_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
     ) -> SirixResult<SirixResponse<U>> {
-        let params = build_read_params(read_args);
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                read_resource(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    params,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                read_resource(
-                    self.scheme.clone(),
-                    self.authority.clone(),
+        self.transport
+            .send_json(
+                endpoints::resource_history(&self.db_name, &self.resource_name, Vec::new())?
+                    .authorization(authorization(&self.auth_channel)?),
+            )
+            .await
+    }
+
+    pub async fn history(&self) -> SirixResult<SirixResponse<History>> {
+        self.history_raw().await
+    }
+
+    /// The commits selected by `query`, in a single request
+    pub async fn history_with(&self, query: HistoryQuery) -> SirixResult<SirixResponse<History>> {
+        let mut response: SirixResponse<History> = self
+            .transport
+            .send_json(
+                endpoints::resource_history(
                     &self.db_name,
-                    self.db_type.clone(),
                     &self.resource_name,
-                    params,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+                    build_history_params(&query),
+                )?
+                .authorization(authorization(&self.auth_channel)?),
+            )
+            .await?;
+        response.body = select_history(response.body, &query);
+        Ok(response)
     }
 
-    pub async fn read_with_metadata_raw<U: DeserializeOwned>(
+    pub async fn diff_raw<U: DeserializeOwned + Send + 'static>(
         &self,
-        meta_type: MetadataType,
-        read_args: ReadArgs,
+        args: DiffArgs,
     ) -> SirixResult<SirixResponse<U>> {
-        let mut params = build_read_params(read_args);
-        params.push(("withMetadata".to_owned(), meta_type.to_string()));
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                read_resource(
-                    self.scheme.clone(),
-                    self.authority.clone(),
+        self.transport
+            .send_json(
+                endpoints::diff_resource(
                     &self.db_name,
-                    self.db_type.clone(),
                     &self.resource_name,
-                    params,
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                read_resource(
-                    self.scheme.clone(),
-                    self.authority.clone(),
+                    build_diff_params(args),
+                )?
+                .authorization(authorization(&self.auth_channel)?),
+            )
+            .await
+    }
+
+    pub async fn diff_string(&self, args: DiffArgs) -> SirixResult<SirixResponse<String>> {
+        self.transport
+            .send_string(
+                endpoints::diff_resource(
+                    &self.db_name,
+                    &self.resource_name,
+                    build_diff_params(args),
+                )?
+                .authorization(authorization(&self.auth_channel)?),
+            )
+            .await
+    }
+
+    pub async fn diff(&self, args: DiffArgs) -> SirixResult<SirixResponse<DiffResult>> {
+        self.diff_raw(args).await
+    }
+
+    /// Insert the data taken from `stream` relative to the node with id `node_id`,
+    /// or replace that node, see `create_from_stream`.
+    pub async fn update_from_stream(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        stream: impl Stream<Item = Bytes> + Send + 'static,
+        etag: Option<String>,
+        progress: impl FnMut(u64) + Send + Unpin + 'static,
+    ) -> SirixResult<SirixResponse<()>> {
+        let body = Body::wrap_stream(ProgressStream::new(stream, progress));
+        let request = self.update_request(node_id, insert, etag).await?;
+        self.transport.send_stream(request, body).await
+    }
+
+    /// Like `update_from_stream`, with the data read from `reader`
+    pub async fn update_from_async_reader(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        reader: impl AsyncRead + Send + 'static,
+        etag: Option<String>,
+        progress: impl FnMut(u64) + Send + Unpin + 'static,
+    ) -> SirixResult<SirixResponse<()>> {
+        let body = Body::wrap_stream(ProgressStream::from_reader(reader, progress));
+        let request = self.update_request(node_id, insert, etag).await?;
+        self.transport.send_stream(request, body).await
+    }
+
+    pub async fn delete(
+        &self,
+        node_and_etag: Option<NodeIdAndEtag>,
+    ) -> SirixResult<SirixResponse<()>> {
+        self.transport
+            .send_no_content(
+                endpoints::resource_delete(
                     &self.db_name,
-                    self.db_type.clone(),
+                    &self.db_type,
                     &self.resource_name,
-                    params,
-                    None,
-                    self.channel.clone(),
-                )
-                .await
+                    node_and_etag,
+                    build_write_params(&self.write_options),
+                )?
+                .authorization(authorization(&self.auth_channel)?),
+            )
+            .await
+    }
+
+    /// `PUT /<db_name>/<name>`, without the initial data
+    fn create_request(&self) -> SirixResult<SirixRequest> {
+        Ok(endpoints::create_resource(
+            &self.db_name,
+            &self.db_type,
+            &self.resource_name,
+            build_write_params(&self.write_options),
+        )?
+        .authorization(authorization(&self.auth_channel)?))
+    }
+
+    /// `POST /<db_name>/<name>` without the data, fetching the ETag of the node if none is given
+    async fn update_request(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        etag: Option<String>,
+    ) -> SirixResult<SirixRequest> {
+        let etag = match etag {
+            Some(etag) => etag,
+            None => self.etag(node_id).await?.body,
+        };
+        Ok(endpoints::update_resource(
+            &self.db_name,
+            &self.db_type,
+            &self.resource_name,
+            NodeIdAndEtag { node_id, etag },
+            insert,
+            build_write_params(&self.write_options),
+        )?
+        .authorization(authorization(&self.auth_channel)?))
+    }
+
+    /// `GET /<db_name>/<name>` with the given parameters
+    fn read_request(&self, params: Vec<(String, String)>) -> SirixResult<SirixRequest> {
+        Ok(
+            endpoints::read_resource(&self.db_name, &self.db_type, &self.resource_name, params)?
+                .authorization(authorization(&self.auth_channel)?),
+        )
+    }
+}
+
+impl<T: Clone> Resource<T> {
+    /// A handle to the same resource whose writes record `options` in their commit
+    pub fn with_write_options(&self, options: WriteOptions) -> Self {
+        Self {
+            write_options: options,
+            ..self.clone()
+        }
+    }
+
+    /// The resource as it was at `revision`, resolved to a revision number.
+    ///
+    /// For a timestamp, this is the latest revision committed at or before it.
+    /// Returns `SirixError::NotFound` if there is no such revision.
+    pub async fn at(&self, revision: SingleRevision) -> SirixResult<RevisionView<T>> {
+        let commit = self.resolve_revision(revision).await?;
+        Ok(RevisionView::new(Arc::new(self.clone()), commit))
+    }
+
+    /// The revisions from the first to the second of `revisions`, see `at`.
+    pub async fn between(&self, revisions: TwoRevisions) -> SirixResult<RevisionRange<T>> {
+        let (first, last) = match revisions {
+            TwoRevisions::Number(first, last) => {
+                (SingleRevision::Number(first), SingleRevision::Number(last))
             }
+            TwoRevisions::Timestamp(first, last) => (
+                SingleRevision::Timestamp(first),
+                SingleRevision::Timestamp(last),
+            ),
+        };
+        let resource = Arc::new(self.clone());
+        Ok(RevisionRange {
+            first: RevisionView::new(resource.clone(), self.resolve_revision(first).await?),
+            last: RevisionView::new(resource, self.resolve_revision(last).await?),
+        })
+    }
+
+    async fn resolve_revision(&self, revision: SingleRevision) -> SirixResult<Commit> {
+        let history = self
+            .history_with(revision_history_query(&revision))
+            .await?
+            .body;
+        commit_at(history, &revision).ok_or(SirixError::NotFound)
+    }
+}
+
+impl<T: Clone + Send + Sync + Unpin + 'static> Resource<T> {
+    /// Lazily stream the commits selected by `query`, newest first,
+    /// `page_size` commits per request.
+    pub fn history_pager(&self, query: HistoryQuery, page_size: u64) -> HistoryPager<T> {
+        HistoryPager::new(self.clone(), query, page_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::super::super::timestamp::parse_timestamp;
+    use super::super::super::types::SingleRevision;
+    use super::super::client::spawn_client;
+    use super::super::error::SirixError;
+    use super::*;
+    use hyper::http::uri::Uri;
+    use mockito::{mock, Matcher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const DIFF_BODY: &str = r#"{"database":"database","resource":"resource","old-revision":1,"new-revision":2,"diffs":[]}"#;
+
+    #[tokio::test]
+    async fn test_update_fetches_etag() {
+        let _m = mock("HEAD", "/database/resource?nodeId=2")
+            .with_status(200)
+            .with_header("etag", "abc123")
+            .create();
+        let _m2 = mock("POST", "/database/resource?nodeId=2&insert=asFirstChild")
+            .match_header("etag", "abc123")
+            .match_header("content-type", "application/json")
+            .match_body(r#"{"key":"value"}"#)
+            .with_status(200)
+            .with_body(r#"{"key":"value"}"#)
+            .create();
+        let resource = test_mocks::async_json_resource("resource");
+        let response = resource
+            .update(2, Insert::Child, r#"{"key":"value"}"#.to_owned(), None)
+            .await
+            .unwrap();
+        assert_eq!(response.body, serde_json::json!({"key": "value"}));
+    }
+
+    #[tokio::test]
+    async fn test_remove_path() {
+        let _m = mock("GET", "/database/resource?maxLevel=3&withMetadata=nodeKey")
+            .with_status(200)
+            .with_body(include_str!("../../tests/resources/metadata/all.json"))
+            .create();
+        let _m2 = mock("HEAD", "/database/resource?nodeId=7")
+            .with_status(200)
+            .with_header("etag", "ghi789")
+            .create();
+        let _m3 = mock("DELETE", "/database/resource?nodeId=7")
+            .match_header("etag", "ghi789")
+            .with_status(204)
+            .create();
+        let resource = test_mocks::async_json_resource("resource");
+        // the object key is deleted, not just its value
+        resource.remove("/baz").await.unwrap();
+        _m3.assert();
+    }
+
+    #[tokio::test]
+    async fn test_read_raw() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Book {
+            title: String,
+        }
+
+        let _m = mock("GET", "/database/read-async?nodeId=2")
+            .match_header("accept", "application/json")
+            .with_status(200)
+            .with_body(r#"[{"title":"Dune"}]"#)
+            .create();
+        let resource = test_mocks::async_json_resource("read-async");
+        let read_args = || ReadArgs {
+            node_id: Some(2),
+            ..ReadArgs::default()
+        };
+        let value = resource.read(read_args()).await.unwrap().body;
+        assert_eq!(value, serde_json::json!([{"title": "Dune"}]));
+        let books: Vec<Book> = resource.read_raw(read_args()).await.unwrap().body;
+        assert_eq!(books[0].title, "Dune");
+    }
+
+    #[tokio::test]
+    async fn test_exists() {
+        let _m = mock("HEAD", "/database/present-async")
+            .with_status(200)
+            .create();
+        let _m2 = mock("HEAD", "/database/absent-async")
+            .with_status(404)
+            .create();
+        let resource = |name: &str| test_mocks::async_json_resource(name);
+        assert!(resource("present-async").exists().await.unwrap().body);
+        assert!(!resource("absent-async").exists().await.unwrap().body);
+    }
+
+    #[tokio::test]
+    async fn test_update_from_stream() {
+        let _m = mock(
+            "POST",
+            "/database/upload-async?nodeId=3&insert=asRightSibling",
+        )
+        .match_header("etag", "abc123")
+        .match_body(r#"{"key":"value"}"#)
+        .with_status(200)
+        .with_body(r#"{"key":"value"}"#)
+        .create();
+        let resource = test_mocks::async_json_resource("upload-async");
+        let chunks = Chunks(vec![
+            Bytes::from_static(br#"{"key":"#),
+            Bytes::from_static(br#""value"}"#),
+        ]);
+        let sent = Arc::new(AtomicU64::new(0));
+        let progress = sent.clone();
+        let response = resource
+            .update_from_stream(
+                3,
+                Insert::Right,
+                chunks,
+                Some("abc123".to_owned()),
+                move |bytes| progress.store(bytes, Ordering::SeqCst),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(sent.load(Ordering::SeqCst), 15);
+    }
+
+    /// Yields its chunks in order
+    struct Chunks(Vec<Bytes>);
+
+    impl Stream for Chunks {
+        type Item = Bytes;
+
+        fn poll_next(
+            self: std::pin::Pin<&mut Self>,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Option<Bytes>> {
+            let chunks = &mut self.get_mut().0;
+            std::task::Poll::Ready(if chunks.is_empty() {
+                None
+            } else {
+                Some(chunks.remove(0))
+            })
         }
     }
 
-    pub async fn read_with_metadata(
-        &self,
-        meta_type: MetadataType,
-        read_args: ReadArgs,
-    ) -> SirixResult<SirixResponse<MetaNode>> {
-        self.read_with_metadata_raw(meta_type, read_args).await
+    #[tokio::test]
+    async fn test_write_options_history() {
+        let m = mock(
+            "DELETE",
+            "/database/resource?nodeId=4&commitMessage=remove+bar&commitTimestamp=2019-05-02T08%3A00%3A00",
+        )
+        .match_header("etag", "def456")
+            .with_status(204)
+            .create();
+        let _m2 = mock("GET", "/database/resource/history")
+            .with_status(200)
+            .with_body(
+                r#"{"history":[{"revisionTimestamp":"2019-05-02T08:00:00","revision":2,"author":"admin","commitMessage":"remove bar"}]}"#,
+            )
+            .create();
+        let resource = test_mocks::async_json_resource("resource");
+        let options = WriteOptions {
+            commit_timestamp: Some(parse_timestamp("2019-05-02T08:00:00").unwrap()),
+            ..WriteOptions::message("remove bar")
+        };
+        resource
+            .with_write_options(options.clone())
+            .delete(Some(NodeIdAndEtag {
+                node_id: 4,
+                etag: "def456".to_owned(),
+            }))
+            .await
+            .unwrap();
+        m.assert();
+        let history = resource.history().await.unwrap().body;
+        assert_eq!(
+            Some(&history[0].commit_message),
+            options.commit_message.as_ref()
+        );
+        assert_eq!(
+            Some(&history[0].revision_timestamp),
+            options.commit_timestamp.as_ref()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_xml_query() {
+        let _m = mock("GET", "/database/books-query?query=count%28%2F%2Fbook%29")
+            .match_header("accept", "application/xml")
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body(
+                r#"<rest:sequence xmlns:rest="https://sirix.io"><rest:item>2</rest:item></rest:sequence>"#,
+            )
+            .create();
+        let resource = test_mocks::async_xml_resource("books-query");
+        let sequence = resource.query("count(//book)", None).await.unwrap().body;
+        let items: Vec<String> = sequence.children().map(Element::text).collect();
+        assert_eq!(items, vec!["2"]);
+    }
+
+    #[tokio::test]
+    async fn test_xml_read_with_metadata() {
+        let _m = mock("GET", "/database/books?withMetadata=true")
+            .match_header("accept", "application/xml")
+            .with_status(200)
+            .with_body(
+                r#"<rest:sequence xmlns:rest="https://sirix.io"><rest:item><shop rest:id="1" rest:hash="3f2a"><book rest:id="2"/></shop></rest:item></rest:sequence>"#,
+            )
+            .create();
+        let resource = test_mocks::async_xml_resource("books");
+        let nodes = resource
+            .read_with_metadata(MetadataType::All, ReadArgs::default())
+            .await
+            .unwrap()
+            .body;
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].metadata.hash.as_deref(), Some("3f2a"));
+        assert_eq!(nodes[0].children[0].node_key(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_query_as() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Titles {
+            rest: Vec<String>,
+        }
+
+        let _m = mock("GET", "/database/resource")
+            .match_query(Matcher::UrlEncoded(
+                "query".into(),
+                "for $b in .[] return $b.title".into(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"rest":["Dune","Emma"]}"#)
+            .create();
+        let _m2 = mock("GET", "/database/resource")
+            .match_query(Matcher::UrlEncoded(
+                "query".into(),
+                "for $b in .[] return $b.year".into(),
+            ))
+            .with_status(200)
+            .with_body(r#"{"rest":[1965,1815]}"#)
+            .create();
+        let resource = test_mocks::async_json_resource("resource");
+        let titles = resource
+            .query_as::<Titles>("for $b in .[] return $b.title", None)
+            .await
+            .unwrap();
+        assert_eq!(titles.body.rest, vec!["Dune", "Emma"]);
+        match resource
+            .query_as::<Titles>("for $b in .[] return $b.year", None)
+            .await
+        {
+            Err(SirixError::JsonError(error)) => assert_eq!(error.path().to_string(), "rest[0]"),
+            response => panic!("expected a JSON error, got {:?}", response),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_diff_url() {
+        let _m = mock(
+            "GET",
+            "/database/resource/diff?startNodeKey=3&maxDepth=2&first-revision=1&second-revision=2",
+        )
+        .with_status(200)
+        .with_body(DIFF_BODY)
+        .create();
+        let resource = test_mocks::async_json_resource("resource");
+        let response = resource
+            .diff(DiffArgs {
+                first_revision: SingleRevision::Number(1),
+                second_revision: SingleRevision::Number(2),
+                node_id: Some(3),
+                max_depth: Some(2),
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.body.old_revision, 1);
+        assert_eq!(response.body.new_revision, 2);
+    }
+
+    #[tokio::test]
+    async fn test_diff_url_with_timestamps() {
+        let _m = mock("GET", "/database/resource/diff")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("first-revision".into(), "2019-05-01T00:00:00".into()),
+                Matcher::UrlEncoded("second-revision".into(), "2019-05-02T00:00:00".into()),
+            ]))
+            .with_status(200)
+            .with_body(DIFF_BODY)
+            .create();
+        let resource = test_mocks::async_json_resource("resource");
+        let response = resource
+            .diff_string(DiffArgs {
+                first_revision: SingleRevision::Timestamp(
+                    parse_timestamp("2019-05-01T00:00:00").unwrap(),
+                ),
+                second_revision: SingleRevision::Timestamp(
+                    parse_timestamp("2019-05-02T00:00:00").unwrap(),
+                ),
+                node_id: None,
+                max_depth: None,
+            })
+            .await;
+        assert_eq!(response.unwrap().body, DIFF_BODY);
+    }
+
+    #[tokio::test]
+    async fn test_query_with_revision() {
+        let _m = mock("GET", "/database/resource")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), "for $i in .[] return $i".into()),
+                Matcher::UrlEncoded("startResultSeqIndex".into(), "2".into()),
+                Matcher::UrlEncoded("revision".into(), "3".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"rest":["a","b"]}"#)
+            .create();
+        let resource = test_mocks::async_json_resource("resource");
+        let response = resource
+            .query(
+                Query {
+                    start_result_seq_index: Some(2),
+                    ..Query::new("for $i in .[] return $i")
+                },
+                Some(RevisionArg::SingleRevision(SingleRevision::Number(3))),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.body, serde_json::json!({"rest": ["a", "b"]}));
+    }
+
+    #[tokio::test]
+    async fn test_encoded_timestamp() {
+        let _m = mock(
+            "GET",
+            "/database/resource/diff?first-revision=2019-05-01T12%3A30%3A00.123&second-revision=2",
+        )
+        .with_status(200)
+        .with_body(DIFF_BODY)
+        .create();
+        let resource = test_mocks::async_json_resource("resource");
+        let response = resource
+            .diff_string(DiffArgs {
+                first_revision: SingleRevision::Timestamp(
+                    parse_timestamp("2019-05-01T12:30:00.123").unwrap(),
+                ),
+                second_revision: SingleRevision::Number(2),
+                node_id: None,
+                max_depth: None,
+            })
+            .await;
+        assert_eq!(response.unwrap().body, DIFF_BODY);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_name() {
+        let resource = test_mocks::async_json_resource("");
+        let response = resource.etag(1).await;
+        assert!(matches!(response, Err(SirixError::InvalidName(_))));
+    }
+
+    #[tokio::test]
+    async fn test_not_authenticated() {
+        let url = &mockito::server_url();
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let (_watch_tx, watch_rx) = tokio::sync::watch::channel(None);
+        let parsed = url.parse::<Uri>().unwrap();
+        let resource = Resource::<Json>::new(
+            "database".to_owned(),
+            "resource".to_owned(),
+            parsed.scheme().unwrap().to_owned(),
+            parsed.authority().unwrap().to_owned(),
+            sender,
+            Some(watch_rx),
+        );
+        let response = resource.etag(1).await;
+        assert!(matches!(response, Err(SirixError::NotAuthenticated)));
     }
 }
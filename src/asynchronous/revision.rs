@@ -0,0 +1,280 @@
+//! Reading a resource as it was at past revisions
+
+use super::super::timestamp::Timestamp;
+use super::super::types::{
+    Commit, DiffArgs, DiffResult, History, HistoryQuery, Json, MetaNode, MetadataType, Query,
+    ReadArgs, RevisionArg, SingleRevision, TwoRevisions, Xml,
+};
+use super::super::xml::{parse_element, XmlMetaNode};
+use super::client::SirixResponse;
+use super::resource::{parse_body, Resource};
+use super::SirixResult;
+use minidom::Element;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// A resource pinned to a single revision, created by `Resource::at`.
+///
+/// Clones share the resource, so views are cheap to pass around.
+/// The revision of `ReadArgs` passed to a view is ignored.
+#[derive(Debug, Clone)]
+pub struct RevisionView<T> {
+    resource: Arc<Resource<T>>,
+    /// the number of the revision
+    pub revision: u64,
+    /// when the revision was committed
+    pub timestamp: Timestamp,
+}
+
+impl<T> RevisionView<T> {
+    pub(crate) fn new(resource: Arc<Resource<T>>, commit: Commit) -> Self {
+        Self {
+            resource,
+            revision: commit.revision as u64,
+            timestamp: commit.revision_timestamp,
+        }
+    }
+
+    fn revision_arg(&self) -> RevisionArg {
+        RevisionArg::SingleRevision(SingleRevision::Number(self.revision))
+    }
+
+    fn read_args(&self, read_args: ReadArgs) -> ReadArgs {
+        ReadArgs {
+            revision: Some(self.revision_arg()),
+            ..read_args
+        }
+    }
+
+    /// The ETag of the node with the given id in this revision
+    pub async fn etag(&self, node_id: u128) -> SirixResult<SirixResponse<String>> {
+        self.resource
+            .etag_at(node_id, Some(self.revision_arg()))
+            .await
+    }
+
+    /// The changes from this revision to `other`
+    pub async fn diff_to(&self, other: &RevisionView<T>) -> SirixResult<SirixResponse<DiffResult>> {
+        self.resource
+            .diff(DiffArgs {
+                first_revision: SingleRevision::Number(self.revision),
+                second_revision: SingleRevision::Number(other.revision),
+                node_id: None,
+                max_depth: None,
+            })
+            .await
+    }
+}
+
+impl RevisionView<Json> {
+    pub async fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Value>> {
+        self.resource.read(self.read_args(read_args)).await
+    }
+
+    pub async fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<MetaNode>> {
+        self.resource
+            .read_with_metadata(meta_type, self.read_args(read_args))
+            .await
+    }
+
+    /// Run a query with this revision of the resource as its context item
+    pub async fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Value>> {
+        self.resource.query(query, Some(self.revision_arg())).await
+    }
+}
+
+impl RevisionView<Xml> {
+    pub async fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Element>> {
+        self.resource.read(self.read_args(read_args)).await
+    }
+
+    pub async fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<Vec<XmlMetaNode>>> {
+        self.resource
+            .read_with_metadata(meta_type, self.read_args(read_args))
+            .await
+    }
+
+    /// Run a query with this revision of the resource as its context item
+    pub async fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Element>> {
+        self.resource.query(query, Some(self.revision_arg())).await
+    }
+}
+
+/// The revisions of a resource from `first` to `last`, created by `Resource::between`.
+///
+/// Reads and queries cover every revision in the range, in the form SirixDB
+/// sends them, with each revision wrapped in its number and timestamp.
+#[derive(Debug, Clone)]
+pub struct RevisionRange<T> {
+    pub first: RevisionView<T>,
+    pub last: RevisionView<T>,
+}
+
+impl<T> RevisionRange<T> {
+    fn revisions(&self) -> TwoRevisions {
+        TwoRevisions::Number(self.first.revision, self.last.revision)
+    }
+
+    fn revision_arg(&self) -> RevisionArg {
+        RevisionArg::TwoRevisions(self.revisions())
+    }
+
+    fn read_args(&self, read_args: ReadArgs) -> ReadArgs {
+        ReadArgs {
+            revision: Some(self.revision_arg()),
+            ..read_args
+        }
+    }
+
+    /// The commits of the revisions in the range
+    pub async fn history(&self) -> SirixResult<SirixResponse<History>> {
+        self.first
+            .resource
+            .history_with(HistoryQuery {
+                range: Some(self.revisions()),
+                limit: None,
+            })
+            .await
+    }
+
+    /// The ETag of the node with the given id in the last revision of the range
+    pub async fn etag(&self, node_id: u128) -> SirixResult<SirixResponse<String>> {
+        self.last.etag(node_id).await
+    }
+
+    /// The changes from the first to the last revision
+    pub async fn diff(&self) -> SirixResult<SirixResponse<DiffResult>> {
+        self.first.diff_to(&self.last).await
+    }
+
+    /// The changes from the last revision of the range to `other`
+    pub async fn diff_to(&self, other: &RevisionView<T>) -> SirixResult<SirixResponse<DiffResult>> {
+        self.last.diff_to(other).await
+    }
+}
+
+impl RevisionRange<Json> {
+    pub async fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Value>> {
+        self.first.resource.read(self.read_args(read_args)).await
+    }
+
+    pub async fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<Value>> {
+        self.first
+            .resource
+            .read_with_metadata_raw(meta_type, self.read_args(read_args))
+            .await
+    }
+
+    /// Run a query with each revision in the range as its context item
+    pub async fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Value>> {
+        self.first
+            .resource
+            .query(query, Some(self.revision_arg()))
+            .await
+    }
+}
+
+impl RevisionRange<Xml> {
+    pub async fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Element>> {
+        self.first.resource.read(self.read_args(read_args)).await
+    }
+
+    pub async fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<Element>> {
+        parse_body(
+            self.first
+                .resource
+                .read_with_metadata_string(meta_type, self.read_args(read_args))
+                .await?,
+            parse_element,
+        )
+    }
+
+    /// Run a query with each revision in the range as its context item
+    pub async fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Element>> {
+        self.first
+            .resource
+            .query(query, Some(self.revision_arg()))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn test_revision_range() {
+        let _m = mock(
+            "GET",
+            "/database/revisions-async/history?startRevision=1&endRevision=1",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"history":[{"revisionTimestamp":"2019-05-01T00:00:00","revision":1,"author":"admin","commitMessage":""}]}"#,
+        )
+        .create();
+        let _m2 = mock(
+            "GET",
+            "/database/revisions-async/history?startRevision=3&endRevision=3",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"history":[{"revisionTimestamp":"2019-05-03T00:00:00","revision":3,"author":"admin","commitMessage":""}]}"#,
+        )
+        .create();
+        let _m3 = mock(
+            "GET",
+            "/database/revisions-async/diff?first-revision=1&second-revision=3",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"database":"database","resource":"revisions-async","old-revision":1,"new-revision":3,"diffs":[]}"#,
+        )
+        .create();
+        let _m4 = mock("HEAD", "/database/revisions-async?nodeId=2&revision=1")
+            .with_status(200)
+            .with_header("etag", "def456")
+            .create();
+        let _m5 = mock("GET", "/database/revisions-async")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), "count(.)".into()),
+                Matcher::UrlEncoded("start-revision".into(), "1".into()),
+                Matcher::UrlEncoded("end-revision".into(), "3".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"rest":[2,3,3]}"#)
+            .create();
+        let _m6 = mock("HEAD", "/database/revisions-async?nodeId=2&revision=3")
+            .with_status(200)
+            .with_header("etag", "ghi789")
+            .create();
+        let resource = test_mocks::async_json_resource("revisions-async");
+        let range = resource.between(TwoRevisions::Number(1, 3)).await.unwrap();
+        assert_eq!((range.first.revision, range.last.revision), (1, 3));
+        assert_eq!(range.diff().await.unwrap().body.old_revision, 1);
+        assert_eq!(range.first.etag(2).await.unwrap().body, "def456");
+        assert_eq!(range.etag(2).await.unwrap().body, "ghi789");
+        assert_eq!(
+            range.query("count(.)").await.unwrap().body,
+            serde_json::json!({"rest": [2, 3, 3]})
+        );
+    }
+}
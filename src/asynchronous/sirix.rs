@@ -2,26 +2,31 @@
 
 use crate::types::{Json, Xml};
 
+use super::super::endpoints;
 use super::super::info;
-use super::super::types::{InfoResults, InfoResultsWithResourcesContainer};
-use super::client::{Message, SirixResponse};
+use super::super::transport::Transport;
+use super::super::types::{InfoResults, InfoResultsWithResourcesContainer, Query};
+use super::auth::{authorization, AuthState};
+use super::builder::{BackgroundTasks, SirixBuilder};
+use super::client::{HyperTransport, Message, SirixResponse};
 use super::database::Database;
-use super::http::{delete_all, global_info, global_info_with_resources};
+use super::query::QueryPager;
 use super::SirixResult;
 use hyper::http::uri::{Authority, Scheme, Uri};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::watch::Receiver;
 
 #[derive(Debug, Clone)]
 pub struct Sirix {
-    /// the scheme with which to access the SirixDB server
-    scheme: Scheme,
-    /// the authority at which to access the SirixDB server
-    authority: Authority,
-    /// the message channel for sending HTTP requests
-    channel: Sender<Message>,
+    /// sends requests to the SirixDB server
+    transport: HyperTransport,
     /// the channel containing authentication data
     auth_channel: Option<Receiver<Option<info::TokenData>>>,
+    /// the channel containing the state of the authentication task
+    auth_state: Option<Receiver<AuthState>>,
 }
 
 impl Sirix {
@@ -31,113 +36,136 @@ impl Sirix {
         auth_channel: Option<Receiver<Option<info::TokenData>>>,
     ) -> Self {
         return Self {
-            scheme: base_uri.scheme().unwrap_or(&Scheme::HTTP).clone(),
-            authority: base_uri
-                .authority()
-                .unwrap_or(&Authority::from_static("localhost:9443"))
-                .clone(),
-            channel: channel,
+            transport: HyperTransport::new(
+                base_uri.scheme().unwrap_or(&Scheme::HTTP).clone(),
+                base_uri
+                    .authority()
+                    .unwrap_or(&Authority::from_static("localhost:9443"))
+                    .clone(),
+                channel,
+            ),
             auth_channel: auth_channel,
+            auth_state: None,
         };
     }
 
+    /// Configure a client for the SirixDB server at `base_url`.
+    pub fn builder(base_url: &str) -> SirixBuilder {
+        SirixBuilder::new(base_url)
+    }
+
+    /// Create a client that owns its background tasks, which stop once the last clone
+    /// of the client, and the last database or resource created from it, is dropped.
+    pub(crate) fn with_tasks(
+        scheme: Scheme,
+        authority: Authority,
+        channel: Sender<Message>,
+        auth: Option<(Receiver<Option<info::TokenData>>, Receiver<AuthState>)>,
+        tasks: BackgroundTasks,
+    ) -> Self {
+        let (auth_channel, auth_state) = match auth {
+            Some((auth_channel, auth_state)) => (Some(auth_channel), Some(auth_state)),
+            None => (None, None),
+        };
+        Self {
+            transport: HyperTransport::new(scheme, authority, channel).with_tasks(Arc::new(tasks)),
+            auth_channel,
+            auth_state,
+        }
+    }
+
+    /// The state of the authentication task, if this client started it
+    pub fn auth_state(&self) -> Option<AuthState> {
+        self.auth_state
+            .as_ref()
+            .map(|auth_state| auth_state.borrow().clone())
+    }
+
     pub fn json_database(&self, db_name: String) -> Database<Json> {
-        Database::<Json>::new(
-            db_name,
-            self.scheme.clone(),
-            self.authority.clone(),
-            self.channel.clone(),
-            self.auth_channel.clone(),
-        )
+        Database::<Json>::with_transport(db_name, self.transport.clone(), self.auth_channel.clone())
     }
 
     pub fn xml_database(&self, db_name: String) -> Database<Xml> {
-        Database::<Xml>::new(
-            db_name,
-            self.scheme.clone(),
-            self.authority.clone(),
-            self.channel.clone(),
-            self.auth_channel.clone(),
-        )
+        Database::<Xml>::with_transport(db_name, self.transport.clone(), self.auth_channel.clone())
     }
 
     pub async fn info(&self) -> SirixResult<SirixResponse<InfoResults>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                global_info(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                global_info(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+        self.transport
+            .send_json(endpoints::global_info().authorization(authorization(&self.auth_channel)?))
+            .await
     }
 
     pub async fn info_with_resources(
         &self,
     ) -> SirixResult<SirixResponse<InfoResultsWithResourcesContainer>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                global_info_with_resources(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                global_info_with_resources(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+        self.transport
+            .send_json(
+                endpoints::global_info_with_resources()
+                    .authorization(authorization(&self.auth_channel)?),
+            )
+            .await
     }
 
     pub async fn delete_all(&self) -> SirixResult<SirixResponse<()>> {
-        match self.auth_channel.clone() {
-            Some(watcher) => {
-                let token_data = watcher.borrow().as_ref().unwrap().clone();
-                let token = token_data.token_type + " " + &token_data.access_token;
-                delete_all(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    Some(&token),
-                    self.channel.clone(),
-                )
-                .await
-            }
-            None => {
-                delete_all(
-                    self.scheme.clone(),
-                    self.authority.clone(),
-                    None,
-                    self.channel.clone(),
-                )
-                .await
-            }
-        }
+        self.transport
+            .send_no_content(
+                endpoints::delete_all().authorization(authorization(&self.auth_channel)?),
+            )
+            .await
+    }
+
+    /// Run a global JSONiq or XQuery query.
+    ///
+    /// Accepts a query string, or a `Query` to fetch only part of the result sequence.
+    pub async fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Value>> {
+        self.query_raw(query).await
     }
 
-    // TODO
-    // query
+    /// Lazily stream the result sequence of a query, `page_size` items per request.
+    pub fn query_pager(&self, query: &str, page_size: u128) -> QueryPager {
+        QueryPager::new(self.clone(), query, page_size)
+    }
+
+    pub async fn query_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        query: impl Into<Query>,
+    ) -> SirixResult<SirixResponse<U>> {
+        let request =
+            endpoints::post_query(&query.into())?.authorization(authorization(&self.auth_channel)?);
+        self.transport.send_json(request).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::client::spawn_client;
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[tokio::test]
+    async fn test_query() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(serde_json::json!({
+                "startResultSeqIndex": 0,
+                "endResultSeqIndex": 9,
+                "query": "jn:doc('database','resource')[]"
+            })))
+            .with_status(200)
+            .with_body(r#"{"rest":[1,2,3]}"#)
+            .create();
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let sirix = Sirix::new(url.parse::<Uri>().unwrap(), sender, None);
+        let response = sirix
+            .query(Query {
+                start_result_seq_index: Some(0),
+                end_result_seq_index: Some(9),
+                ..Query::new("jn:doc('database','resource')[]")
+            })
+            .await
+            .unwrap();
+        assert_eq!(response.body, serde_json::json!({"rest": [1, 2, 3]}));
+    }
 }
@@ -0,0 +1,304 @@
+//! This module configures HTTPS connections to the SirixDB server
+
+use super::error::SirixError;
+use super::SirixResult;
+use hyper::client::HttpConnector;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, PrivateKey, RootCertStore, ServerName};
+use rustls_pemfile::Item;
+use std::fmt;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// TLS settings for connecting to SirixDB over HTTPS
+///
+/// The Mozilla root certificates are always trusted, in addition to any CA bundle.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    ca_bundle: Vec<Vec<u8>>,
+    client_certificate: Option<ClientCertificate>,
+    danger_accept_invalid_certs: bool,
+}
+
+/// The PEM-encoded certificate chain and private key to authenticate with
+#[derive(Clone)]
+struct ClientCertificate {
+    cert_chain_pem: Vec<u8>,
+    key_pem: Vec<u8>,
+}
+
+/// Leaves out the private key, so that it does not end up in logs
+impl fmt::Debug for ClientCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientCertificate")
+            .field(
+                "cert_chain_pem",
+                &String::from_utf8_lossy(&self.cert_chain_pem),
+            )
+            .field("key_pem", &"<redacted>")
+            .finish()
+    }
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also trust the PEM-encoded CA certificates in `pem`
+    pub fn ca_bundle(mut self, pem: &[u8]) -> Self {
+        self.ca_bundle.push(pem.to_vec());
+        self
+    }
+
+    /// Authenticate with the PEM-encoded certificate chain and private key
+    pub fn client_certificate(mut self, cert_chain_pem: &[u8], key_pem: &[u8]) -> Self {
+        self.client_certificate = Some(ClientCertificate {
+            cert_chain_pem: cert_chain_pem.to_vec(),
+            key_pem: key_pem.to_vec(),
+        });
+        self
+    }
+
+    /// Accept any server certificate, including self-signed and expired ones.
+    ///
+    /// This disables all protection TLS offers, and is only meant for development.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.danger_accept_invalid_certs = accept;
+        self
+    }
+
+    /// Wrap `http` in a connector that speaks HTTPS for `https` URLs, and plain HTTP
+    /// otherwise.
+    pub fn https_connector(
+        &self,
+        http: HttpConnector,
+    ) -> SirixResult<HttpsConnector<HttpConnector>> {
+        let mut http = http;
+        http.enforce_http(false);
+        Ok(HttpsConnectorBuilder::new()
+            .with_tls_config(self.client_config()?)
+            .https_or_http()
+            .enable_http1()
+            .wrap_connector(http))
+    }
+
+    fn client_config(&self) -> SirixResult<ClientConfig> {
+        let mut roots = RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                anchor.subject,
+                anchor.spki,
+                anchor.name_constraints,
+            )
+        }));
+        for pem in &self.ca_bundle {
+            for certificate in read_certificates(pem)? {
+                roots.add(&certificate)?;
+            }
+        }
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots);
+        let mut config = match &self.client_certificate {
+            Some(client) => builder.with_client_auth_cert(
+                read_certificates(&client.cert_chain_pem)?,
+                read_key(&client.key_pem)?,
+            )?,
+            None => builder.with_no_client_auth(),
+        };
+        if self.danger_accept_invalid_certs {
+            config
+                .dangerous()
+                .set_certificate_verifier(Arc::new(AcceptAnyCertificate));
+        }
+        Ok(config)
+    }
+}
+
+fn read_certificates(pem: &[u8]) -> SirixResult<Vec<Certificate>> {
+    let certificates = rustls_pemfile::certs(&mut &pem[..])
+        .map_err(|err| SirixError::InvalidCertificate(err.to_string()))?;
+    if certificates.is_empty() {
+        return Err(SirixError::InvalidCertificate(
+            "no certificates found in PEM data".to_owned(),
+        ));
+    }
+    Ok(certificates.into_iter().map(Certificate).collect())
+}
+
+fn read_key(pem: &[u8]) -> SirixResult<PrivateKey> {
+    let items = rustls_pemfile::read_all(&mut &pem[..])
+        .map_err(|err| SirixError::InvalidCertificate(err.to_string()))?;
+    items
+        .into_iter()
+        .find_map(|item| match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            SirixError::InvalidCertificate("no private key found in PEM data".to_owned())
+        })
+}
+
+/// Accepts every server certificate, see `TlsConfig::danger_accept_invalid_certs`
+struct AcceptAnyCertificate;
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::client::spawn_client;
+    use super::super::sirix::Sirix;
+    use super::*;
+    use hyper::Client;
+    use rustls::server::AllowAnyAuthenticatedClient;
+    use rustls::ServerConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_rustls::TlsAcceptor;
+
+    struct TestCertificate {
+        cert_pem: String,
+        key_pem: String,
+    }
+
+    fn generate_certificate(name: &str) -> TestCertificate {
+        let certificate = rcgen::generate_simple_self_signed(vec![name.to_owned()]).unwrap();
+        TestCertificate {
+            cert_pem: certificate.serialize_pem().unwrap(),
+            key_pem: certificate.serialize_private_key_pem(),
+        }
+    }
+
+    /// Serve a single HTTPS request with an empty JSON array, returning the server URL.
+    async fn serve_once(server_config: ServerConfig) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = TlsAcceptor::from(Arc::new(server_config));
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            // handshake failures are what some tests expect
+            if let Ok(mut stream) = acceptor.accept(stream).await {
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(read) => request.extend_from_slice(&buffer[..read]),
+                    }
+                }
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\n[]",
+                    )
+                    .await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        format!("https://localhost:{}", port)
+    }
+
+    /// A server presenting `server`, requiring a client certificate signed by one of
+    /// `client_roots` if given.
+    fn server_config(
+        server: &TestCertificate,
+        client_roots: Option<RootCertStore>,
+    ) -> ServerConfig {
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match client_roots {
+            Some(roots) => {
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+            }
+            None => builder.with_no_client_auth(),
+        };
+        builder
+            .with_single_cert(
+                read_certificates(server.cert_pem.as_bytes()).unwrap(),
+                read_key(server.key_pem.as_bytes()).unwrap(),
+            )
+            .unwrap()
+    }
+
+    async fn info(url: &str, tls: TlsConfig) -> SirixResult<()> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        let connector = tls.https_connector(HttpConnector::new())?;
+        spawn_client(Client::builder().build(connector), receiver);
+        let sirix = Sirix::new(url.parse().unwrap(), sender, None);
+        sirix.info().await.map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn test_ca_bundle() {
+        let server = generate_certificate("localhost");
+        let url = serve_once(server_config(&server, None)).await;
+        let tls = TlsConfig::new().ca_bundle(server.cert_pem.as_bytes());
+        assert!(info(&url, tls).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_certificate() {
+        let server = generate_certificate("localhost");
+        let url = serve_once(server_config(&server, None)).await;
+        let response = info(&url, TlsConfig::new()).await;
+        assert!(matches!(response, Err(SirixError::ConnectionError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_danger_accept_invalid_certs() {
+        let server = generate_certificate("not-localhost");
+        let url = serve_once(server_config(&server, None)).await;
+        let sirix = Sirix::builder(&url)
+            .tls(TlsConfig::new().danger_accept_invalid_certs(true))
+            .build()
+            .await
+            .unwrap();
+        assert!(sirix.info().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate() {
+        let server = generate_certificate("localhost");
+        let client = generate_certificate("client");
+        let mut client_roots = RootCertStore::empty();
+        for certificate in read_certificates(client.cert_pem.as_bytes()).unwrap() {
+            client_roots.add(&certificate).unwrap();
+        }
+        let url = serve_once(server_config(&server, Some(client_roots))).await;
+        let tls = TlsConfig::new()
+            .ca_bundle(server.cert_pem.as_bytes())
+            .client_certificate(client.cert_pem.as_bytes(), client.key_pem.as_bytes());
+        assert!(info(&url, tls).await.is_ok());
+    }
+
+    #[test]
+    fn test_debug_redacts_key() {
+        let client = generate_certificate("client");
+        let tls = TlsConfig::new()
+            .client_certificate(client.cert_pem.as_bytes(), client.key_pem.as_bytes());
+        let key = client.key_pem.lines().nth(1).unwrap();
+        let debug = format!("{:?}", Sirix::builder("https://localhost").tls(tls));
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains(key));
+    }
+
+    #[test]
+    fn test_invalid_pem() {
+        let tls = TlsConfig::new().ca_bundle(b"not a certificate");
+        let response = tls.https_connector(HttpConnector::new());
+        assert!(matches!(response, Err(SirixError::InvalidCertificate(_))));
+    }
+}
@@ -0,0 +1,161 @@
+//! Streaming request bodies
+
+use bytes::Bytes;
+use futures_core::Stream;
+use hyper::Body;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::sync::oneshot;
+
+/// The size of the chunks read from an `AsyncRead`
+const CHUNK_SIZE: usize = 64 * 1024;
+
+type ChunkStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+/// Reports the number of bytes taken from a body so far, as hyper sends them
+pub struct ProgressStream<F> {
+    stream: ChunkStream,
+    progress: F,
+    sent: u64,
+}
+
+impl<F: FnMut(u64) + Send + Unpin + 'static> ProgressStream<F> {
+    pub fn new(stream: impl Stream<Item = Bytes> + Send + 'static, progress: F) -> Self {
+        Self {
+            stream: Box::pin(Chunks {
+                stream: Box::pin(stream),
+            }),
+            progress,
+            sent: 0,
+        }
+    }
+
+    /// The contents of `reader`, in chunks of 64 KiB
+    pub fn from_reader(reader: impl AsyncRead + Send + 'static, progress: F) -> Self {
+        Self {
+            stream: Box::pin(ReaderChunks {
+                reader: Box::pin(reader),
+                buf: vec![0; CHUNK_SIZE],
+            }),
+            progress,
+            sent: 0,
+        }
+    }
+}
+
+impl<F: FnMut(u64) + Unpin> Stream for ProgressStream<F> {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let chunk = this.stream.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &chunk {
+            this.sent += chunk.len() as u64;
+            (this.progress)(this.sent);
+        }
+        chunk
+    }
+}
+
+/// Signals once hyper has taken all of a body, so the upload itself is not timed
+pub struct SentBody {
+    body: Body,
+    sent: Option<oneshot::Sender<()>>,
+}
+
+impl SentBody {
+    pub fn new(body: Body) -> (Self, oneshot::Receiver<()>) {
+        let (sent, receiver) = oneshot::channel();
+        (
+            Self {
+                body,
+                sent: Some(sent),
+            },
+            receiver,
+        )
+    }
+}
+
+impl Stream for SentBody {
+    type Item = hyper::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let chunk = Pin::new(&mut this.body).poll_next(cx);
+        if let Poll::Ready(None) = chunk {
+            if let Some(sent) = this.sent.take() {
+                let _ = sent.send(());
+            }
+        }
+        chunk
+    }
+}
+
+/// A stream of chunks that cannot fail, as hyper expects a stream of results
+struct Chunks<S> {
+    stream: Pin<Box<S>>,
+}
+
+impl<S: Stream<Item = Bytes>> Stream for Chunks<S> {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .stream
+            .as_mut()
+            .poll_next(cx)
+            .map(|chunk| chunk.map(Ok))
+    }
+}
+
+struct ReaderChunks<R> {
+    reader: Pin<Box<R>>,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead> Stream for ReaderChunks<R> {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut buf = ReadBuf::new(&mut this.buf);
+        match this.reader.as_mut().poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(())) if buf.filled().is_empty() => Poll::Ready(None),
+            Poll::Ready(Ok(())) => Poll::Ready(Some(Ok(Bytes::copy_from_slice(buf.filled())))),
+            Poll::Ready(Err(err)) => Poll::Ready(Some(Err(err))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::poll_fn;
+    use std::sync::{Arc, Mutex};
+
+    async fn collect<F: FnMut(u64) + Unpin>(mut stream: ProgressStream<F>) -> Vec<u8> {
+        let mut body = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        body
+    }
+
+    #[tokio::test]
+    async fn test_progress_from_reader() {
+        let data = vec![b'x'; CHUNK_SIZE + 10];
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let sent = reports.clone();
+        let stream = ProgressStream::from_reader(io::Cursor::new(data.clone()), move |bytes| {
+            sent.lock().unwrap().push(bytes)
+        });
+        assert_eq!(collect(stream).await, data);
+        assert_eq!(
+            *reports.lock().unwrap(),
+            vec![CHUNK_SIZE as u64, CHUNK_SIZE as u64 + 10]
+        );
+    }
+}
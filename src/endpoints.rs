@@ -0,0 +1,272 @@
+//! The endpoints of the SirixDB HTTP interface, shared by both clients.
+
+use super::transport::{Method, SirixRequest};
+use super::types::{DbType, Insert, NodeIdAndEtag, Query};
+use thiserror::Error;
+
+/// A database or resource name that cannot be part of a SirixDB URL
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid database or resource name: {0:?}")]
+pub struct InvalidName(pub String);
+
+/// Check that `name` is usable as a database or resource name.
+///
+/// Names must not be empty, `.` or `..`, and must not contain slashes or control
+/// characters. Other characters are percent-encoded when the request is sent.
+pub fn validate_name(name: &str) -> Result<&str, InvalidName> {
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name
+            .chars()
+            .any(|c| c == '/' || c == '\\' || c.is_control());
+    if invalid {
+        return Err(InvalidName(name.to_owned()));
+    }
+    Ok(name)
+}
+
+/// `GET /`
+pub fn global_info() -> SirixRequest {
+    SirixRequest::new(Method::Get, &[]).header("accept", "application/json")
+}
+
+/// `GET /?withResources=true`
+pub fn global_info_with_resources() -> SirixRequest {
+    global_info().query("withResources", true)
+}
+
+/// `DELETE /`
+///
+/// Careful - will delete all databases and associated resources.
+pub fn delete_all() -> SirixRequest {
+    SirixRequest::new(Method::Delete, &[])
+}
+
+/// `POST /`
+///
+/// Submit a global query
+pub fn post_query(query: &Query) -> serde_json::Result<SirixRequest> {
+    Ok(SirixRequest::new(Method::Post, &[])
+        .header("content-type", "application/json")
+        .header("accept", "application/json")
+        .body(serde_json::to_string(query)?))
+}
+
+/// `PUT /<db_name>`
+///
+/// Create a new database with name `db_name` and type `db_type`.
+pub fn create_database(db_name: &str, db_type: &DbType) -> Result<SirixRequest, InvalidName> {
+    Ok(SirixRequest::new(Method::Put, &[validate_name(db_name)?]).header("content-type", db_type))
+}
+
+/// `GET /<db_name>`
+///
+/// Return information about database with name `db_name`.
+pub fn database_info(db_name: &str) -> Result<SirixRequest, InvalidName> {
+    Ok(SirixRequest::new(Method::Get, &[validate_name(db_name)?])
+        .header("accept", "application/json"))
+}
+
+/// `DELETE /<db_name>`
+///
+/// Delete database with name `db_name`.
+pub fn delete_database(db_name: &str) -> Result<SirixRequest, InvalidName> {
+    Ok(SirixRequest::new(
+        Method::Delete,
+        &[validate_name(db_name)?],
+    ))
+}
+
+/// `HEAD /<db_name>/<name>`
+///
+/// Head request to resource, to determine if resource exists
+pub fn resource_exists(
+    db_name: &str,
+    db_type: &DbType,
+    name: &str,
+) -> Result<SirixRequest, InvalidName> {
+    Ok(resource(Method::Head, db_name, name, &[])?.header("content-type", db_type))
+}
+
+/// `PUT /<db_name>/<name>`
+///
+/// Put request to create resource, overwrites if it already exists.
+/// The initial data is sent as the body.
+pub fn create_resource(
+    db_name: &str,
+    db_type: &DbType,
+    name: &str,
+    params: Vec<(String, String)>,
+) -> Result<SirixRequest, InvalidName> {
+    Ok(resource(Method::Put, db_name, name, &[])?
+        .header("content-type", db_type)
+        .queries(params))
+}
+
+/// `GET /<db_name>/<name>`
+///
+/// Read resource with given parameters
+pub fn read_resource(
+    db_name: &str,
+    db_type: &DbType,
+    name: &str,
+    params: Vec<(String, String)>,
+) -> Result<SirixRequest, InvalidName> {
+    Ok(resource(Method::Get, db_name, name, &[])?
+        .header("accept", db_type)
+        .queries(params))
+}
+
+/// `GET /<db_name>/<name>/history`
+///
+/// Get the commits in the history of the resource, as JSON for both database types
+pub fn resource_history(
+    db_name: &str,
+    name: &str,
+    params: Vec<(String, String)>,
+) -> Result<SirixRequest, InvalidName> {
+    Ok(resource(Method::Get, db_name, name, &["history"])?
+        .header("accept", "application/json")
+        .queries(params))
+}
+
+/// `GET /<db_name>/<name>/diff`
+///
+/// Get diffs for the given revisions
+pub fn diff_resource(
+    db_name: &str,
+    name: &str,
+    params: Vec<(String, String)>,
+) -> Result<SirixRequest, InvalidName> {
+    Ok(resource(Method::Get, db_name, name, &["diff"])?.queries(params))
+}
+
+/// `HEAD /<db_name>/<name>?nodeId=<node_id>`
+///
+/// Fetch the ETag of the node with the given id
+pub fn get_etag(
+    db_name: &str,
+    db_type: &DbType,
+    name: &str,
+    node_id: u128,
+    params: Vec<(String, String)>,
+) -> Result<SirixRequest, InvalidName> {
+    Ok(resource(Method::Head, db_name, name, &[])?
+        .header("accept", db_type)
+        .query("nodeId", node_id)
+        .queries(params))
+}
+
+/// `POST /<db_name>/<name>?nodeId=<node_id>&insert=<insert>`
+///
+/// Update resource according to given parameters, with the data sent as the body
+pub fn update_resource(
+    db_name: &str,
+    db_type: &DbType,
+    name: &str,
+    node_and_etag: NodeIdAndEtag,
+    insert: Insert,
+    params: Vec<(String, String)>,
+) -> Result<SirixRequest, InvalidName> {
+    Ok(resource(Method::Post, db_name, name, &[])?
+        .header("content-type", db_type)
+        .header("etag", node_and_etag.etag)
+        .query("nodeId", node_and_etag.node_id)
+        .query("insert", insert)
+        .queries(params))
+}
+
+/// `DELETE /<db_name>/<name>`
+///
+/// Delete the node with the given id and ETag, or the whole resource
+pub fn resource_delete(
+    db_name: &str,
+    db_type: &DbType,
+    name: &str,
+    node_and_etag: Option<NodeIdAndEtag>,
+    params: Vec<(String, String)>,
+) -> Result<SirixRequest, InvalidName> {
+    let request = resource(Method::Delete, db_name, name, &[])?.header("content-type", db_type);
+    let request = match node_and_etag {
+        // delete node in resource
+        Some(node_and_etag) => request
+            .header("etag", node_and_etag.etag)
+            .query("nodeId", node_and_etag.node_id),
+        // delete the resource itself
+        None => request,
+    };
+    Ok(request.queries(params))
+}
+
+/// A request to `/<db_name>/<name>`, followed by the segments in `rest`
+fn resource(
+    method: Method,
+    db_name: &str,
+    name: &str,
+    rest: &[&str],
+) -> Result<SirixRequest, InvalidName> {
+    let mut path = vec![validate_name(db_name)?, validate_name(name)?];
+    path.extend_from_slice(rest);
+    Ok(SirixRequest::new(method, &path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::Json;
+    use super::*;
+
+    #[test]
+    fn test_update_resource() {
+        let request = update_resource(
+            "database",
+            &DbType::Json(Json),
+            "resource",
+            NodeIdAndEtag {
+                node_id: 2,
+                etag: "abc123".to_owned(),
+            },
+            Insert::Child,
+            vec![("commitMessage".to_owned(), "add child".to_owned())],
+        )
+        .unwrap()
+        .body("{}".to_owned());
+        assert_eq!(request.method, Method::Post);
+        assert_eq!(
+            request.path_and_query(),
+            "/database/resource?nodeId=2&insert=asFirstChild&commitMessage=add+child"
+        );
+        assert_eq!(
+            request.headers,
+            vec![
+                ("content-type".to_owned(), "application/json".to_owned()),
+                ("etag".to_owned(), "abc123".to_owned()),
+            ]
+        );
+        assert_eq!(request.body.as_deref(), Some("{}"));
+    }
+
+    #[test]
+    fn test_encoded_names() {
+        let request = database_info("my database").unwrap();
+        assert_eq!(request.path_and_query(), "/my%20database");
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        for name in &["", ".", "..", "a/b", "a\\b", "a\nb"] {
+            assert_eq!(
+                resource_history("database", name, Vec::new()),
+                Err(InvalidName(name.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_global_info_with_resources() {
+        assert_eq!(
+            global_info_with_resources().path_and_query(),
+            "/?withResources=true"
+        );
+    }
+}
@@ -1,8 +1,8 @@
 //! This module contains types for holding token information and the various node types
 
 use serde::{Deserialize, Serialize};
-use serde_with::serde_as;
-use std::{fmt, io, str::FromStr};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::{fmt, io, str::FromStr, time::Duration};
 
 /// A specific connection token
 #[derive(Debug, Deserialize, Clone)]
@@ -20,6 +20,31 @@ pub struct TokenData {
     pub token_type: String,
 }
 
+impl TokenData {
+    /// Whether the refresh token can still be used, `elapsed` after it was issued.
+    /// A `refresh_expires_in` of zero means the refresh token does not expire.
+    pub fn refresh_token_valid(&self, elapsed: Duration) -> bool {
+        self.refresh_expires_in == 0 || elapsed.as_secs() + 10 < self.refresh_expires_in
+    }
+
+    /// How long to wait before renewing the access token: 10 seconds before it
+    /// expires, but at least half its lifetime and at least one second, so short-lived
+    /// tokens do not make the authentication loop spin against the token endpoint.
+    pub fn refresh_delay(&self) -> Duration {
+        Duration::from_secs(
+            self.expires_in
+                .saturating_sub(10)
+                .max(self.expires_in / 2)
+                .max(1),
+        )
+    }
+
+    /// The value of the `authorization` header for this token
+    pub fn authorization(&self) -> String {
+        format!("{} {}", self.token_type, self.access_token)
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct TokenPostData {
     pub username: String,
@@ -28,43 +53,31 @@ pub struct TokenPostData {
 }
 
 /// All the various node types
-#[serde_as]
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(untagged)]
 pub enum NodeType {
-    NodeTypeContainer,
-    NodeTypePrimitive,
+    Container(NodeTypeContainer),
+    Primitive(NodeTypePrimitive),
 }
 
-#[serde_as]
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
+/// The types of nodes that have children
+#[derive(Debug, Clone, PartialEq, DeserializeFromStr, SerializeDisplay)]
 pub enum NodeTypeContainer {
-    #[serde_as(as = "DisplayFromStr")]
     Object,
-    #[serde_as(as = "DisplayFromStr")]
     Array,
 }
 
-#[serde_as]
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
+/// The types of nodes without children, including object keys
+#[derive(Debug, Clone, PartialEq, DeserializeFromStr, SerializeDisplay)]
 pub enum NodeTypePrimitive {
-    #[serde_as(as = "DisplayFromStr")]
     BooleanValue,
-    #[serde_as(as = "DisplayFromStr")]
     NullValue,
-    #[serde_as(as = "DisplayFromStr")]
     NumberValue,
-    #[serde_as(as = "DisplayFromStr")]
     ObjectBooleanValue,
-    #[serde_as(as = "DisplayFromStr")]
     ObjectKey,
-    #[serde_as(as = "DisplayFromStr")]
     ObjectNullValue,
-    #[serde_as(as = "DisplayFromStr")]
+    ObjectNumberValue,
     ObjectStringValue,
-    #[serde_as(as = "DisplayFromStr")]
     StringValue,
 }
 
@@ -78,6 +91,7 @@ impl fmt::Display for NodeTypePrimitive {
             ObjectBooleanValue => "OBJECT_BOOLEAN_VALUE",
             ObjectKey => "OBJECT_KEY",
             ObjectNullValue => "OBJECT_NULL_VALUE",
+            ObjectNumberValue => "OBJECT_NUMBER_VALUE",
             ObjectStringValue => "OBJECT_STRING_VALUE",
             StringValue => "STRING_VALUE",
         };
@@ -96,6 +110,7 @@ impl FromStr for NodeTypePrimitive {
             "OBJECT_BOOLEAN_VALUE" => Ok(ObjectBooleanValue),
             "OBJECT_KEY" => Ok(ObjectKey),
             "OBJECT_NULL_VALUE" => Ok(ObjectNullValue),
+            "OBJECT_NUMBER_VALUE" => Ok(ObjectNumberValue),
             "OBJECT_STRING_VALUE" => Ok(ObjectStringValue),
             "STRING_VALUE" => Ok(StringValue),
             _ => Err(io::Error::new(
@@ -0,0 +1,70 @@
+//! Typed JSON resources.
+//!
+//! Errors report the path of the field that failed, such as `books[2].title`.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A value that could not be (de)serialized, with the path to the failing field
+pub type JsonError = serde_path_to_error::Error<serde_json::Error>;
+
+/// Deserialize a JSON document into `T`
+pub fn deserialize<T: DeserializeOwned>(json: &str) -> Result<T, JsonError> {
+    serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_str(json))
+}
+
+/// Serialize `value` into a JSON document
+pub fn serialize<T: Serialize + ?Sized>(value: &T) -> Result<String, JsonError> {
+    let mut json = Vec::new();
+    serde_path_to_error::serialize(value, &mut serde_json::Serializer::new(&mut json))?;
+    // serde_json only writes valid UTF-8
+    Ok(String::from_utf8(json).expect("serde_json produced invalid UTF-8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Book {
+        title: String,
+        year: u16,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, PartialEq)]
+    struct Shop {
+        books: Vec<Book>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let shop = Shop {
+            books: vec![Book {
+                title: "Dune".to_owned(),
+                year: 1965,
+            }],
+        };
+        let json = serialize(&shop).unwrap();
+        assert_eq!(json, r#"{"books":[{"title":"Dune","year":1965}]}"#);
+        assert_eq!(deserialize::<Shop>(&json).unwrap(), shop);
+    }
+
+    #[test]
+    fn test_deserialize_error_path() {
+        let error = deserialize::<Shop>(
+            r#"{"books":[{"title":"Dune","year":1965},{"title":"Emma","year":"1815"}]}"#,
+        )
+        .unwrap_err();
+        assert_eq!(error.path().to_string(), "books[1].year");
+    }
+
+    #[test]
+    fn test_serialize_error_path() {
+        let mut map = BTreeMap::new();
+        map.insert(vec![1], 1);
+        let error = serialize(&vec![map]).unwrap_err();
+        assert_eq!(error.path().to_string(), "[0]");
+    }
+}
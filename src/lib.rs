@@ -6,10 +6,18 @@ pub mod asynchronous;
 #[cfg(feature = "sync")]
 pub mod synchronous;
 
+#[cfg(any(feature = "sync", feature = "async"))]
+pub mod endpoints;
 pub mod info;
+pub mod json;
 pub mod mock;
+pub mod timestamp;
+#[cfg(any(feature = "sync", feature = "async"))]
+pub mod transport;
 pub mod types;
+#[cfg(any(feature = "sync", feature = "async"))]
 mod utils;
+pub mod xml;
 
 #[cfg(test)]
 mod tests {}
@@ -1,6 +1,14 @@
 #[cfg(test)]
 pub mod test_mocks {
+    #[cfg(feature = "async")]
+    use super::super::asynchronous::{client::spawn_client, resource::Resource as AsyncResource};
     use super::super::info::TokenData;
+    #[cfg(feature = "sync")]
+    use super::super::synchronous::resource::Resource;
+    #[cfg(any(feature = "sync", feature = "async"))]
+    use super::super::types::{Json, Xml};
+    #[cfg(feature = "async")]
+    use hyper::Uri;
     use mockito::{mock, Mock};
     use serde_json::ser::to_string;
 
@@ -43,4 +51,60 @@ pub mod test_mocks {
             .with_body(to_string(&response).unwrap())
             .create()
     }
+
+    /// A JSON resource in `database` on the mockito server
+    #[cfg(feature = "sync")]
+    pub fn json_resource(name: &str) -> Resource<Json> {
+        Resource::<Json>::new(
+            "database".to_owned(),
+            name.to_owned(),
+            mockito::server_url(),
+            ureq::agent(),
+            None,
+        )
+    }
+
+    /// An XML resource in `database` on the mockito server
+    #[cfg(feature = "sync")]
+    pub fn xml_resource(name: &str) -> Resource<Xml> {
+        Resource::<Xml>::new(
+            "database".to_owned(),
+            name.to_owned(),
+            mockito::server_url(),
+            ureq::agent(),
+            None,
+        )
+    }
+
+    /// Like `json_resource`, with its own client task, so it must be called in a runtime
+    #[cfg(feature = "async")]
+    pub fn async_json_resource(name: &str) -> AsyncResource<Json> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let parsed = mockito::server_url().parse::<Uri>().unwrap();
+        AsyncResource::<Json>::new(
+            "database".to_owned(),
+            name.to_owned(),
+            parsed.scheme().unwrap().to_owned(),
+            parsed.authority().unwrap().to_owned(),
+            sender,
+            None,
+        )
+    }
+
+    /// Like `xml_resource`, with its own client task, so it must be called in a runtime
+    #[cfg(feature = "async")]
+    pub fn async_xml_resource(name: &str) -> AsyncResource<Xml> {
+        let (sender, receiver) = tokio::sync::mpsc::channel(32);
+        spawn_client(hyper::Client::new(), receiver);
+        let parsed = mockito::server_url().parse::<Uri>().unwrap();
+        AsyncResource::<Xml>::new(
+            "database".to_owned(),
+            name.to_owned(),
+            parsed.scheme().unwrap().to_owned(),
+            parsed.authority().unwrap().to_owned(),
+            sender,
+            None,
+        )
+    }
 }
@@ -1,11 +1,14 @@
-use super::{client::request, error::SirixError};
+use super::{
+    client::request,
+    error::{SirixError, SirixResult},
+};
 use crate::info::{TokenData, TokenPostData};
 
-use log::{error, info};
+use log::{error, info, warn};
 use serde_json::ser::to_string;
-use std::thread::sleep;
-use std::time::Duration;
-use std::{sync::Arc, sync::RwLock, thread::spawn};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::{spawn, JoinHandle};
+use std::time::{Duration, Instant};
 
 fn authenticate(
     agent: ureq::Agent,
@@ -16,14 +19,11 @@ fn authenticate(
     let req = agent.post(endpoint).set("content-type", "application/json");
     let response = request::<TokenData>(
         req,
-        Some(
-            &to_string(&TokenPostData {
-                username: username.to_string(),
-                password: password.to_string(),
-                grant_type: "password".to_string(),
-            })
-            .unwrap(),
-        ),
+        Some(&to_string(&TokenPostData {
+            username: username.to_string(),
+            password: password.to_string(),
+            grant_type: "password".to_string(),
+        })?),
     );
     match response {
         Ok(response) => Ok(response.body),
@@ -45,82 +45,313 @@ fn refresh(
     }
 }
 
+/// The state of the background authentication loop
+#[derive(Debug, Clone)]
+pub enum AuthState {
+    /// the first login with credentials has not completed yet
+    Authenticating,
+    /// a valid token is available
+    Authenticated,
+    /// the token is about to expire and is being renewed
+    Refreshing,
+    /// the last attempt to obtain a token failed, and will be retried
+    Failed(Arc<SirixError>),
+}
+
+/// The shared slot holding the current token
+pub type TokenLock = Arc<RwLock<Option<TokenData>>>;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// State shared between an `AuthHandle` and its background thread
+#[derive(Debug)]
+struct Shared {
+    status: Mutex<Status>,
+    changed: Condvar,
+}
+
+#[derive(Debug)]
+struct Status {
+    state: AuthState,
+    /// why the first login failed, handed back to the caller of `auth`
+    login_error: Option<SirixError>,
+    shutdown: bool,
+}
+
+impl Shared {
+    fn update<F: FnOnce(&mut Status)>(&self, f: F) {
+        match self.status.lock() {
+            Ok(mut status) => f(&mut status),
+            Err(_) => error!("authentication state lock is poisoned"),
+        }
+        self.changed.notify_all();
+    }
+
+    fn set_state(&self, state: AuthState) {
+        self.update(|status| status.state = state);
+    }
+
+    /// Sleep for `duration`, returning early with `true` once shutdown is requested.
+    fn wait(&self, duration: Duration) -> bool {
+        let status = match self.status.lock() {
+            Ok(status) => status,
+            Err(_) => return true,
+        };
+        match self
+            .changed
+            .wait_timeout_while(status, duration, |status| !status.shutdown)
+        {
+            Ok((status, _)) => status.shutdown,
+            Err(_) => true,
+        }
+    }
+}
+
+/// Obtain a new token, preferring the refresh token while it is still valid.
+fn renew(
+    agent: &ureq::Agent,
+    endpoint: &str,
+    username: &str,
+    password: &str,
+    token_data: &TokenData,
+    issued_at: Instant,
+) -> Result<TokenData, SirixError> {
+    if !token_data.refresh_token_valid(issued_at.elapsed()) {
+        info!("refresh token expired, authenticating with credentials");
+        return authenticate(agent.clone(), endpoint, username, password);
+    }
+    match refresh(agent.clone(), endpoint, token_data.clone()) {
+        Ok(token_data) => Ok(token_data),
+        Err(err) => {
+            warn!(
+                "token refresh failed, authenticating with credentials: {}",
+                err
+            );
+            authenticate(agent.clone(), endpoint, username, password)
+        }
+    }
+}
+
 fn begin_authentication_loop(
     agent: ureq::Agent,
-    lock: Arc<RwLock<Option<TokenData>>>,
-    base_url: &str,
+    lock: TokenLock,
+    shared: Arc<Shared>,
+    endpoint: &str,
     username: &str,
     password: &str,
 ) {
-    let endpoint = &format!("{}/token", base_url);
-    let token_data = authenticate(agent.clone(), endpoint, username, password);
-    let token_data = match token_data {
+    let mut token_data = match authenticate(agent.clone(), endpoint, username, password) {
         Ok(token_data) => {
             info!("authentication with credentials successful");
-            //response_sender.send(Some(token_data.clone())).unwrap();
-            Some(token_data)
+            token_data
         }
-        Err(_) => {
-            error!("authentication with credentials failed");
-            None
+        Err(err) => {
+            error!("authentication with credentials failed: {}", err);
+            shared.update(|status| status.login_error = Some(err));
+            return;
         }
     };
-    let mut token_data = token_data.clone().unwrap();
-    {
-        let mut l = lock.write().unwrap();
-        *l = Some(token_data.clone());
-    }
+    let mut issued_at = Instant::now();
+    store_token(&lock, &token_data);
+    shared.set_state(AuthState::Authenticated);
 
     loop {
-        sleep(Duration::from_secs(token_data.expires_in.clone() - 10));
-        let refresh_response = refresh(agent.clone(), endpoint, token_data.clone());
-        match refresh_response {
-            Ok(new_token_data) => {
-                info!("authentication with credentials successful");
-                token_data = new_token_data;
-                {
-                    let mut l = lock.write().unwrap();
-                    *l = Some(token_data.clone());
+        if shared.wait(token_data.refresh_delay()) {
+            return;
+        }
+        shared.set_state(AuthState::Refreshing);
+        let mut result = renew(&agent, endpoint, username, password, &token_data, issued_at);
+        let mut backoff = INITIAL_BACKOFF;
+        loop {
+            match result {
+                Ok(new_token_data) => {
+                    info!("token renewal successful");
+                    token_data = new_token_data;
+                    issued_at = Instant::now();
+                    store_token(&lock, &token_data);
+                    shared.set_state(AuthState::Authenticated);
+                    break;
+                }
+                Err(err) => {
+                    error!("token renewal failed, retrying in {:?}: {}", backoff, err);
+                    shared.set_state(AuthState::Failed(Arc::new(err)));
+                    if shared.wait(backoff) {
+                        return;
+                    }
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    result = authenticate(agent.clone(), endpoint, username, password);
                 }
             }
-            Err(_) => {
-                error!("authentication with credentials failed")
+        }
+    }
+}
+
+fn store_token(lock: &RwLock<Option<TokenData>>, token_data: &TokenData) {
+    match lock.write() {
+        Ok(mut l) => *l = Some(token_data.clone()),
+        Err(_) => error!("authentication data lock is poisoned"),
+    }
+}
+
+/// Read the current token, failing if authentication has not (yet) succeeded.
+pub(crate) fn read_token(lock: &RwLock<Option<TokenData>>) -> SirixResult<TokenData> {
+    match lock.read() {
+        Ok(token_data) => token_data.clone().ok_or(SirixError::NotAuthenticated),
+        Err(_) => Err(SirixError::NotAuthenticated),
+    }
+}
+
+/// The `authorization` header for the current token, if the client authenticates
+pub(crate) fn authorization(lock: &Option<TokenLock>) -> SirixResult<Option<String>> {
+    match lock {
+        Some(lock) => Ok(Some(read_token(lock)?.authorization())),
+        None => Ok(None),
+    }
+}
+
+/// A handle to the background thread that keeps the authentication token fresh.
+///
+/// The thread is stopped and joined when the handle is dropped.
+#[derive(Debug)]
+pub struct AuthHandle {
+    token: TokenLock,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AuthHandle {
+    /// The slot holding the current token, as accepted by `Sirix::new`
+    pub fn token(&self) -> TokenLock {
+        Arc::clone(&self.token)
+    }
+
+    /// The current state of the authentication thread
+    pub fn state(&self) -> AuthState {
+        match self.shared.status.lock() {
+            Ok(status) => status.state.clone(),
+            Err(_) => AuthState::Failed(Arc::new(SirixError::NotAuthenticated)),
+        }
+    }
+
+    /// Stop the authentication thread and wait for it to finish.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn request_shutdown(&self) {
+        self.shared.update(|status| status.shutdown = true);
+    }
+
+    fn stop(&mut self) {
+        self.request_shutdown();
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("authentication thread panicked");
             }
-        };
+        }
     }
 }
 
+impl Drop for AuthHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Log in with the given credentials and keep the token fresh in a background thread.
+///
+/// Waits up to five seconds for the first token; see `auth_with_timeout`.
 pub fn auth(
     agent: ureq::Agent,
     base_url: &str,
     username: &str,
     password: &str,
-) -> Arc<RwLock<Option<TokenData>>> {
-    let base_url = base_url.to_owned();
+) -> SirixResult<AuthHandle> {
+    auth_with_timeout(agent, base_url, username, password, Duration::from_secs(5))
+}
+
+/// Log in with the given credentials and keep the token fresh in a background thread.
+///
+/// Fails if the first login is rejected, or if it does not complete within `timeout`.
+pub fn auth_with_timeout(
+    agent: ureq::Agent,
+    base_url: &str,
+    username: &str,
+    password: &str,
+    timeout: Duration,
+) -> SirixResult<AuthHandle> {
+    let endpoint = format!("{}/token", base_url.trim_end_matches('/'));
+    start_auth(agent, &endpoint, username, password, timeout)
+}
+
+/// Start the authentication thread against the given token endpoint.
+pub(crate) fn start_auth(
+    agent: ureq::Agent,
+    endpoint: &str,
+    username: &str,
+    password: &str,
+    timeout: Duration,
+) -> SirixResult<AuthHandle> {
+    let endpoint = endpoint.to_owned();
     let username = username.to_owned();
     let password = password.to_owned();
     let lock = Arc::new(RwLock::new(None));
+    let shared = Arc::new(Shared {
+        status: Mutex::new(Status {
+            state: AuthState::Authenticating,
+            login_error: None,
+            shutdown: false,
+        }),
+        changed: Condvar::new(),
+    });
     let cloned_lock = Arc::clone(&lock);
-    spawn(move || {
-        begin_authentication_loop(agent, cloned_lock, &base_url, &username, &password);
+    let cloned_shared = Arc::clone(&shared);
+    let thread = spawn(move || {
+        begin_authentication_loop(
+            agent,
+            cloned_lock,
+            cloned_shared,
+            &endpoint,
+            &username,
+            &password,
+        );
     });
-    for _ in 0..200 {
-        let local_lock = lock.clone();
-        let l = local_lock.read().unwrap();
-        match *l {
-            Some(_) => {
-                break;
-            }
-            None => sleep(Duration::from_millis(25)),
-        }
+    let mut handle = AuthHandle {
+        token: lock,
+        shared: Arc::clone(&shared),
+        thread: Some(thread),
+    };
+
+    let status = shared
+        .status
+        .lock()
+        .map_err(|_| SirixError::NotAuthenticated)?;
+    let (mut status, _) = shared
+        .changed
+        .wait_timeout_while(status, timeout, |status| {
+            matches!(status.state, AuthState::Authenticating) && status.login_error.is_none()
+        })
+        .map_err(|_| SirixError::NotAuthenticated)?;
+    if let Some(err) = status.login_error.take() {
+        return Err(err);
     }
-    return lock;
+    if matches!(status.state, AuthState::Authenticating) {
+        drop(status);
+        // the login request may still be in flight, so let the thread finish on its own
+        handle.request_shutdown();
+        handle.thread = None;
+        return Err(SirixError::AuthTimeout);
+    }
+    drop(status);
+    Ok(handle)
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::super::mock::test_mocks;
     use super::*;
+    use std::thread::sleep;
 
     #[test]
     fn test_authenticate() {
@@ -155,21 +386,90 @@ mod tests {
         let _m2 = test_mocks::mock_refresh();
         let agent = ureq::agent();
 
-        let lock = auth(agent, url, "admin", "admin");
-        sleep(Duration::from_millis(10));
+        let handle = auth(agent, url, "admin", "admin").unwrap();
+        assert!(matches!(handle.state(), AuthState::Authenticated));
+        let lock = handle.token();
         {
             assert_eq!(
                 (*(lock.clone().read().unwrap())).clone().unwrap(),
                 test_mocks::get_token_data()
             );
         }
-        sleep(Duration::from_secs(
-            test_mocks::get_token_data().expires_in - 10,
-        ));
+        sleep(test_mocks::get_token_data().refresh_delay());
         sleep(Duration::from_millis(10));
         assert_ne!(
             (*(lock.clone().read().unwrap())).clone().unwrap(),
             test_mocks::get_token_data()
         )
     }
+
+    #[test]
+    fn test_refresh_delay() {
+        let mut token_data = test_mocks::get_token_data();
+        for (expires_in, delay) in [(300, 290), (13, 6), (10, 5), (1, 1), (0, 1)] {
+            token_data.expires_in = expires_in;
+            assert_eq!(token_data.refresh_delay(), Duration::from_secs(delay));
+        }
+    }
+
+    #[test]
+    fn test_auth_failure() {
+        let url = &mockito::server_url();
+        let _m = mockito::mock("POST", "/token").with_status(401).create();
+        let agent = ureq::agent();
+
+        let response = auth(agent, url, "admin", "admin");
+        assert!(matches!(response, Err(SirixError::Unauthorized)));
+    }
+
+    #[test]
+    fn test_refresh_falls_back_to_credentials() {
+        let url = &mockito::server_url();
+        let m = test_mocks::mock_auth().expect_at_least(2);
+        let _m2 = mockito::mock("POST", "/token")
+            .match_body(mockito::Matcher::Regex("refresh_token".to_string()))
+            .with_status(400)
+            .create();
+        let agent = ureq::agent();
+
+        let handle = auth(agent, url, "admin", "admin").unwrap();
+        sleep(test_mocks::get_token_data().refresh_delay());
+        sleep(Duration::from_millis(100));
+        assert!(matches!(handle.state(), AuthState::Authenticated));
+        assert_eq!(
+            read_token(&handle.token()).unwrap(),
+            test_mocks::get_token_data()
+        );
+        m.assert();
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let url = &mockito::server_url();
+        let _m = test_mocks::mock_auth();
+        let agent = ureq::agent();
+
+        let handle = auth(agent, url, "admin", "admin").unwrap();
+        let lock = handle.token();
+        let start = Instant::now();
+        handle.shutdown();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // the last token stays readable after shutdown
+        assert!(read_token(&lock).is_ok());
+    }
+
+    #[test]
+    fn test_auth_timeout() {
+        let url = &mockito::server_url();
+        let _m = mockito::mock("POST", "/token")
+            .with_body_from_fn(|w| {
+                sleep(Duration::from_millis(200));
+                w.write_all(to_string(&test_mocks::get_token_data()).unwrap().as_bytes())
+            })
+            .create();
+        let agent = ureq::agent();
+
+        let response = auth_with_timeout(agent, url, "admin", "admin", Duration::from_millis(50));
+        assert!(matches!(response, Err(SirixError::AuthTimeout)));
+    }
 }
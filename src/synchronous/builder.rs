@@ -0,0 +1,220 @@
+//! This module contains a builder for configuring a `Sirix` client
+
+use super::auth::start_auth;
+use super::error::SirixResult;
+use super::sirix::Sirix;
+use std::time::Duration;
+
+/// Configures and connects a `Sirix` client.
+///
+/// ```no_run
+/// use sirix_rust_client::synchronous::sirix::Sirix;
+///
+/// let sirix = Sirix::builder("http://localhost:9443")
+///     .credentials("admin", "admin")
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct SirixBuilder {
+    base_url: String,
+    credentials: Option<(String, String)>,
+    token_endpoint: Option<String>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    auth_timeout: Duration,
+    user_agent: Option<String>,
+    default_headers: Vec<(String, String)>,
+}
+
+impl SirixBuilder {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_owned(),
+            credentials: None,
+            token_endpoint: None,
+            timeout: None,
+            connect_timeout: None,
+            auth_timeout: Duration::from_secs(5),
+            user_agent: None,
+            default_headers: Vec::new(),
+        }
+    }
+
+    /// The URL of the SirixDB server
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_owned();
+        self
+    }
+
+    /// Log in with these credentials, and keep the token fresh in the background
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_owned(), password.to_owned()));
+        self
+    }
+
+    /// The URL to request tokens from, `<base_url>/token` by default
+    pub fn token_endpoint(mut self, token_endpoint: &str) -> Self {
+        self.token_endpoint = Some(token_endpoint.to_owned());
+        self
+    }
+
+    /// The maximum time for a whole request, including reading the response
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The maximum time to wait for a connection to the server
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// The maximum time `build` waits for the first token, five seconds by default
+    pub fn auth_timeout(mut self, timeout: Duration) -> Self {
+        self.auth_timeout = timeout;
+        self
+    }
+
+    /// The value of the `user-agent` header
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_owned());
+        self
+    }
+
+    /// A header to send with every request that does not already set it
+    pub fn default_header(mut self, name: &str, value: &str) -> Self {
+        self.default_headers
+            .push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Create the client, logging in first if credentials were given.
+    // the header middleware has to return `ureq::Error`, which is large
+    #[allow(clippy::result_large_err)]
+    pub fn build(self) -> SirixResult<Sirix> {
+        let mut builder = ureq::AgentBuilder::new();
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.timeout_connect(timeout);
+        }
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if !self.default_headers.is_empty() {
+            let headers = self.default_headers;
+            builder = builder.middleware(
+                move |mut request: ureq::Request, next: ureq::MiddlewareNext| {
+                    for (name, value) in &headers {
+                        if request.header(name).is_none() {
+                            request = request.set(name, value);
+                        }
+                    }
+                    next.handle(request)
+                },
+            );
+        }
+        let agent = builder.build();
+
+        let base_url = self.base_url.trim_end_matches('/').to_owned();
+        let auth_handle = match self.credentials {
+            Some((username, password)) => {
+                let token_endpoint = self
+                    .token_endpoint
+                    .unwrap_or_else(|| format!("{}/token", base_url));
+                Some(start_auth(
+                    agent.clone(),
+                    &token_endpoint,
+                    &username,
+                    &password,
+                    self.auth_timeout,
+                )?)
+            }
+            None => None,
+        };
+        Ok(Sirix::with_auth_handle(base_url, agent, auth_handle))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::super::auth::AuthState;
+    use super::super::error::SirixError;
+    use super::*;
+    use mockito::mock;
+    use std::thread::sleep;
+
+    #[test]
+    fn test_build() {
+        let url = &mockito::server_url();
+        let _m = test_mocks::mock_auth();
+        let _m2 = mock("GET", "/")
+            .match_header(
+                "authorization",
+                test_mocks::get_token_data().authorization().as_str(),
+            )
+            .match_header("user-agent", "sirix-test")
+            .match_header("x-tenant", "test")
+            .with_body(r#"{"databases":[]}"#)
+            .create();
+
+        let sirix = Sirix::builder(url)
+            .credentials("admin", "admin")
+            .user_agent("sirix-test")
+            .default_header("x-tenant", "test")
+            .build()
+            .unwrap();
+        assert!(matches!(sirix.auth_state(), Some(AuthState::Authenticated)));
+        assert!(sirix.info_raw::<serde_json::Value>().is_ok());
+    }
+
+    #[test]
+    fn test_database_outlives_client() {
+        let url = &mockito::server_url();
+        let _m = test_mocks::mock_auth();
+        let _m2 = test_mocks::mock_refresh();
+        let _m3 = mock("GET", "/outlives")
+            .match_header("authorization", "bearer refreshed")
+            .with_body(r#"{"resources":[]}"#)
+            .create();
+
+        let sirix = Sirix::builder(url)
+            .credentials("admin", "admin")
+            .build()
+            .unwrap();
+        let database = sirix.json_database("outlives".to_owned());
+        drop(sirix);
+        // the authentication thread keeps refreshing the token for the database
+        sleep(test_mocks::get_token_data().refresh_delay());
+        sleep(Duration::from_millis(100));
+        assert!(database.info_raw::<serde_json::Value>().is_ok());
+    }
+
+    #[test]
+    fn test_build_token_endpoint() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/auth/token")
+            .with_body(serde_json::to_string(&test_mocks::get_token_data()).unwrap())
+            .create();
+
+        let sirix = Sirix::builder(url)
+            .credentials("admin", "admin")
+            .token_endpoint(&format!("{}/auth/token", url))
+            .build()
+            .unwrap();
+        assert!(matches!(sirix.auth_state(), Some(AuthState::Authenticated)));
+    }
+
+    #[test]
+    fn test_build_auth_failure() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/token").with_status(401).create();
+
+        let response = Sirix::builder(url).credentials("admin", "admin").build();
+        assert!(matches!(response, Err(SirixError::Unauthorized)));
+    }
+}
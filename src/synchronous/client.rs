@@ -1,7 +1,10 @@
+use super::super::transport::{SirixRequest, Transport};
+use super::auth::AuthHandle;
 use super::error::{SirixError, SirixResult};
 use serde::de::DeserializeOwned;
 use serde_json;
 use std::io::Read;
+use std::sync::Arc;
 use ureq;
 
 #[derive(Debug)]
@@ -33,7 +36,8 @@ pub fn request<T: DeserializeOwned>(
                 Err(err) => Err(SirixError::FormatError(err)),
             }
         }
-        Err(err) => Err(SirixError::ConnectionError(err)),
+        Err(ureq::Error::Status(status, resp)) => Err(status_error(status, resp)),
+        Err(err) => Err(SirixError::from(err)),
     }
 }
 
@@ -51,13 +55,229 @@ pub fn request_string(
             let status = resp.status();
             let etag = resp.header("etag").map(String::from);
             let mut buf: Vec<u8> = vec![];
-            resp.into_reader().read_to_end(&mut buf).unwrap();
+            resp.into_reader().read_to_end(&mut buf)?;
             Ok(SirixResponse {
                 body: String::from_utf8_lossy(&buf).into_owned(),
                 status,
                 etag,
             })
         }
-        Err(err) => Err(SirixError::ConnectionError(err)),
+        Err(ureq::Error::Status(status, resp)) => Err(status_error(status, resp)),
+        Err(err) => Err(SirixError::from(err)),
+    }
+}
+
+pub fn request_no_content(
+    req: ureq::Request,
+    body: Option<&str>,
+) -> SirixResult<SirixResponse<()>> {
+    let response = match body {
+        Some(data) => req.send_string(data),
+        None => req.call(),
+    };
+    no_content_response(response)
+}
+
+/// Like `request_no_content`, streaming the body from `reader`
+pub fn request_reader(req: ureq::Request, reader: impl Read) -> SirixResult<SirixResponse<()>> {
+    no_content_response(req.send(reader))
+}
+
+fn no_content_response(
+    response: Result<ureq::Response, ureq::Error>,
+) -> SirixResult<SirixResponse<()>> {
+    match response {
+        Ok(resp) => Ok(SirixResponse {
+            status: resp.status(),
+            etag: resp.header("etag").map(String::from),
+            body: (),
+        }),
+        Err(ureq::Error::Status(status, resp)) => Err(status_error(status, resp)),
+        Err(err) => Err(SirixError::from(err)),
+    }
+}
+
+/// Sends requests to the SirixDB server at `base_url` with a `ureq::Agent`
+#[derive(Debug, Clone)]
+pub struct UreqTransport {
+    agent: ureq::Agent,
+    base_url: String,
+    /// the authentication thread, if a builder started it; it stops once the last
+    /// transport sharing it is dropped
+    auth_handle: Option<Arc<AuthHandle>>,
+}
+
+impl UreqTransport {
+    pub fn new(agent: ureq::Agent, base_url: &str) -> Self {
+        Self {
+            agent,
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            auth_handle: None,
+        }
+    }
+
+    /// Keep the authentication thread running for as long as this transport, or a clone
+    /// of it, exists
+    pub(crate) fn with_auth_handle(mut self, auth_handle: Arc<AuthHandle>) -> Self {
+        self.auth_handle = Some(auth_handle);
+        self
+    }
+
+    pub(crate) fn auth_handle(&self) -> Option<&AuthHandle> {
+        self.auth_handle.as_deref()
+    }
+
+    pub fn agent(&self) -> &ureq::Agent {
+        &self.agent
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn prepare(&self, request: &SirixRequest) -> ureq::Request {
+        let url = format!("{}{}", self.base_url, request.path_and_query());
+        let mut req = self.agent.request(request.method.as_str(), &url);
+        for (name, value) in &request.headers {
+            req = req.set(name, value);
+        }
+        req
+    }
+}
+
+impl Transport for UreqTransport {
+    type Output<T> = SirixResult<SirixResponse<T>>;
+    type Stream<'a> = Box<dyn Read + 'a>;
+
+    fn send_json<T: DeserializeOwned + Send + 'static>(
+        &self,
+        req: SirixRequest,
+    ) -> SirixResult<SirixResponse<T>> {
+        request(self.prepare(&req), req.body.as_deref())
+    }
+
+    fn send_string(&self, req: SirixRequest) -> SirixResult<SirixResponse<String>> {
+        request_string(self.prepare(&req), req.body.as_deref())
+    }
+
+    fn send_no_content(&self, req: SirixRequest) -> SirixResult<SirixResponse<()>> {
+        request_no_content(self.prepare(&req), req.body.as_deref())
+    }
+
+    fn send_stream<'a>(
+        &self,
+        req: SirixRequest,
+        body: Box<dyn Read + 'a>,
+    ) -> SirixResult<SirixResponse<()>> {
+        request_reader(self.prepare(&req), body)
+    }
+}
+
+fn status_error(status: u16, resp: ureq::Response) -> SirixError {
+    let etag = resp.header("etag").map(String::from);
+    let body = resp.into_string().unwrap_or_default();
+    SirixError::from_status(status, etag, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::mock;
+    use std::io::Write;
+
+    #[test]
+    fn test_not_found() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/missing").with_status(404).create();
+        let response = request::<()>(ureq::get(&format!("{}/missing", url)), None);
+        assert!(matches!(response, Err(SirixError::NotFound)));
+    }
+
+    #[test]
+    fn test_precondition_failed() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/database/resource")
+            .with_status(412)
+            .with_header("etag", "current")
+            .create();
+        let response = request_string(
+            ureq::post(&format!("{}/database/resource", url)),
+            Some("{}"),
+        );
+        match response {
+            Err(SirixError::PreconditionFailed { etag }) => {
+                assert_eq!(etag.as_deref(), Some("current"))
+            }
+            other => panic!("expected PreconditionFailed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_server_error() {
+        let url = &mockito::server_url();
+        let _m = mock("DELETE", "/database")
+            .with_status(500)
+            .with_body("boom")
+            .create();
+        let response = request_no_content(ureq::delete(&format!("{}/database", url)), None);
+        match response {
+            Err(SirixError::ServerError { status, body }) => {
+                assert_eq!(status, 500);
+                assert_eq!(body, "boom");
+            }
+            other => panic!("expected ServerError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_client_error() {
+        let url = &mockito::server_url();
+        let _m = mock("PUT", "/database/unsupported")
+            .with_status(415)
+            .with_body("unsupported media type")
+            .create();
+        let response =
+            request_no_content(ureq::put(&format!("{}/database/unsupported", url)), None);
+        match response {
+            Err(SirixError::ClientError { status, body }) => {
+                assert_eq!(status, 415);
+                assert_eq!(body, "unsupported media type");
+            }
+            other => panic!("expected ClientError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_conflict() {
+        let url = &mockito::server_url();
+        let _m = mock("PUT", "/conflict")
+            .with_status(409)
+            .with_body("resource is locked")
+            .create();
+        let response = request_no_content(ureq::put(&format!("{}/conflict", url)), None);
+        match response {
+            Err(SirixError::Conflict { body }) => assert_eq!(body, "resource is locked"),
+            other => panic!("expected Conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_truncated_body() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/database/resource")
+            .with_status(200)
+            .with_body_from_fn(|w| {
+                w.write_all(b"{\"partial\":")?;
+                Err(std::io::Error::new(std::io::ErrorKind::Other, "dropped"))
+            })
+            .create();
+        let response = request_string(ureq::get(&format!("{}/database/resource", url)), None);
+        assert!(matches!(response, Err(SirixError::Io(_))));
+    }
+
+    #[test]
+    fn test_connection_refused() {
+        let response = request_string(ureq::get("http://127.0.0.1:1/"), None);
+        assert!(matches!(response, Err(SirixError::ConnectionError(_))));
     }
 }
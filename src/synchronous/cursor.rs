@@ -0,0 +1,143 @@
+//! Paging through the children of a node
+
+use super::super::types::{
+    HistoryQuery, Json, MetaNode, MetadataType, ReadArgs, RevisionArg, SingleRevision,
+};
+use super::error::SirixResult;
+use super::resource::Resource;
+use std::collections::VecDeque;
+
+/// Iterates over the children of an array or object, fetching `page_size` children per request.
+///
+/// Children are read with their node keys, and each page continues after
+/// the last node of the previous one, so only a single page is held in memory at a time.
+/// Without a `revision`, every page is read from the latest revision when the first page is.
+/// The children of an object are its `MetaNode::ObjectKey`s.
+#[derive(Debug)]
+pub struct ChildrenCursor {
+    resource: Resource<Json>,
+    node_id: Option<u128>,
+    page_size: u64,
+    revision: Option<SingleRevision>,
+    last_node_key: Option<u64>,
+    buffer: VecDeque<MetaNode>,
+    done: bool,
+}
+
+impl ChildrenCursor {
+    pub fn new(
+        resource: Resource<Json>,
+        node_id: Option<u128>,
+        page_size: u64,
+        revision: Option<SingleRevision>,
+    ) -> Self {
+        Self {
+            resource,
+            node_id,
+            page_size: page_size.max(1),
+            revision,
+            last_node_key: None,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// The latest revision of the resource, if it has any
+    fn latest_revision(&self) -> SirixResult<Option<SingleRevision>> {
+        let query = HistoryQuery {
+            range: None,
+            limit: Some(1),
+        };
+        let history = self.resource.history_with(query)?.body;
+        Ok(history
+            .last()
+            .map(|commit| SingleRevision::Number(commit.revision as u64)))
+    }
+
+    fn fetch_page(&mut self) -> SirixResult<()> {
+        if self.revision.is_none() && self.last_node_key.is_none() {
+            self.revision = self.latest_revision()?;
+        }
+        let read_args = ReadArgs {
+            node_id: self.node_id,
+            max_level: Some(1),
+            revision: self.revision.clone().map(RevisionArg::SingleRevision),
+            top_level_limit: Some(self.page_size),
+            top_level_skip_last_node: self.last_node_key,
+        };
+        let children = self
+            .resource
+            .read_with_metadata(MetadataType::Key, read_args)?
+            .body
+            .into_children();
+        match children.last() {
+            Some(last) if children.len() as u64 >= self.page_size => {
+                self.last_node_key = Some(last.node_key() as u64);
+            }
+            _ => self.done = true,
+        }
+        self.buffer.extend(children);
+        Ok(())
+    }
+}
+
+impl Iterator for ChildrenCursor {
+    type Item = SirixResult<MetaNode>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(child) = self.buffer.pop_front() {
+                return Some(Ok(child));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(err) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::*;
+    use mockito::mock;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_children_cursor() {
+        let _m = mock(
+            "GET",
+            "/database/cursor?nodeId=3&maxLevel=1&nextTopLevelNodes=2&revision=1&withMetadata=nodeKey",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"metadata":{"nodeKey":3},"value":[
+                {"metadata":{"nodeKey":4},"value":"bar"},
+                {"metadata":{"nodeKey":5},"value":null}
+            ]}"#,
+        )
+        .create();
+        let _m2 = mock(
+            "GET",
+            "/database/cursor?nodeId=3&maxLevel=1&nextTopLevelNodes=2&lastTopLevelNodeKey=5&revision=1&withMetadata=nodeKey",
+        )
+        .with_status(200)
+        .with_body(r#"{"metadata":{"nodeKey":3},"value":[{"metadata":{"nodeKey":6},"value":2.33}]}"#)
+        .expect(1)
+        .create();
+        let resource = test_mocks::json_resource("cursor");
+        let children = resource
+            .children_cursor(Some(3), 2, Some(SingleRevision::Number(1)))
+            .collect::<SirixResult<Vec<MetaNode>>>()
+            .unwrap();
+        let keys: Vec<usize> = children.iter().map(MetaNode::node_key).collect();
+        assert_eq!(keys, vec![4, 5, 6]);
+        let values: Vec<Value> = children.into_iter().map(Value::from).collect();
+        assert_eq!(values, vec![json!("bar"), Value::Null, json!(2.33)]);
+        _m2.assert();
+    }
+}
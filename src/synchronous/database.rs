@@ -1,10 +1,12 @@
 use serde::de::DeserializeOwned;
 
+use super::super::endpoints;
 use super::super::info;
+use super::super::transport::Transport;
 use super::super::types::{DbInfo, DbType, Json, Xml};
-use super::client::SirixResponse;
+use super::auth::authorization;
+use super::client::{SirixResponse, UreqTransport};
 use super::error::SirixResult;
-use super::http::{create_database, delete_database, get_database_info, get_database_info_string};
 use super::resource::Resource;
 use std::{sync::Arc, sync::RwLock};
 
@@ -15,10 +17,8 @@ pub struct Database<T> {
     pub db_name: String,
     /// The type of that database.
     pub db_type: DbType,
-    /// the url for the SirixDB server
-    base_uri: String,
-    /// a ureq::Agent
-    agent: ureq::Agent,
+    /// sends requests to the SirixDB server
+    transport: UreqTransport,
     /// a reference to authentication data
     auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
 }
@@ -28,73 +28,30 @@ impl<T> Database<T> {
         self.info_raw()
     }
 
-    pub fn info_raw<U: DeserializeOwned>(&self) -> SirixResult<SirixResponse<U>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                get_database_info(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                )
-            }
-            None => get_database_info(self.agent.clone(), None, &self.base_uri, &self.db_name),
-        }
+    pub fn info_raw<U: DeserializeOwned + Send + 'static>(&self) -> SirixResult<SirixResponse<U>> {
+        self.transport.send_json(
+            endpoints::database_info(&self.db_name)?.authorization(authorization(&self.auth_lock)?),
+        )
     }
 
     pub fn info_string(&self) -> SirixResult<SirixResponse<String>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                get_database_info_string(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                )
-            }
-            None => {
-                get_database_info_string(self.agent.clone(), None, &self.base_uri, &self.db_name)
-            }
-        }
+        self.transport.send_string(
+            endpoints::database_info(&self.db_name)?.authorization(authorization(&self.auth_lock)?),
+        )
     }
 
     pub fn delete(&self) -> SirixResult<SirixResponse<()>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                delete_database(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                )
-            }
-            None => delete_database(self.agent.clone(), None, &self.base_uri, &self.db_name),
-        }
+        self.transport.send_no_content(
+            endpoints::delete_database(&self.db_name)?
+                .authorization(authorization(&self.auth_lock)?),
+        )
     }
 
     pub fn create(&self) -> SirixResult<SirixResponse<()>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                create_database(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                )
-            }
-            None => create_database(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                self.db_type.clone(),
-            ),
-        }
+        self.transport.send_no_content(
+            endpoints::create_database(&self.db_name, &self.db_type)?
+                .authorization(authorization(&self.auth_lock)?),
+        )
     }
 }
 
@@ -104,23 +61,29 @@ impl Database<Json> {
         base_uri: String,
         agent: ureq::Agent,
         auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
+    ) -> Self {
+        Self::with_transport(db_name, UreqTransport::new(agent, &base_uri), auth_lock)
+    }
+
+    pub(crate) fn with_transport(
+        db_name: String,
+        transport: UreqTransport,
+        auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
     ) -> Self {
         Self {
             _t: Json,
-            db_name: db_name,
+            db_name,
             db_type: DbType::Json(Json),
-            base_uri: base_uri,
-            agent: agent,
-            auth_lock: auth_lock,
+            transport,
+            auth_lock,
         }
     }
 
     pub fn resource(&self, name: String) -> Resource<Json> {
-        Resource::<Json>::new(
+        Resource::<Json>::with_transport(
             self.db_name.clone(),
             name,
-            self.base_uri.clone(),
-            self.agent.clone(),
+            self.transport.clone(),
             self.auth_lock.clone(),
         )
     }
@@ -132,23 +95,29 @@ impl Database<Xml> {
         base_uri: String,
         agent: ureq::Agent,
         auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
+    ) -> Self {
+        Self::with_transport(db_name, UreqTransport::new(agent, &base_uri), auth_lock)
+    }
+
+    pub(crate) fn with_transport(
+        db_name: String,
+        transport: UreqTransport,
+        auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
     ) -> Self {
         Self {
             _t: Xml,
             db_name,
             db_type: DbType::XML(Xml),
-            base_uri,
-            agent,
+            transport,
             auth_lock,
         }
     }
 
     pub fn resource(&self, name: String) -> Resource<Xml> {
-        Resource::<Xml>::new(
+        Resource::<Xml>::with_transport(
             self.db_name.clone(),
             name,
-            self.base_uri.clone(),
-            self.agent.clone(),
+            self.transport.clone(),
             self.auth_lock.clone(),
         )
     }
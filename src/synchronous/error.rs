@@ -1,5 +1,9 @@
 //! Error type
 
+use super::super::endpoints::InvalidName;
+use super::super::json::JsonError;
+use super::super::types::{InvalidPath, UnexpectedQueryResult};
+use super::super::xml::XmlError;
 use thiserror::Error;
 use ureq;
 
@@ -7,11 +11,73 @@ use ureq;
 pub enum SirixError {
     // #[error("Unable to communicate with SirixDB server")]
     #[error(transparent)]
-    ConnectionError(#[from] ureq::Error),
+    ConnectionError(Box<ureq::Error>),
     //#[error("Malformed JSON response")]
     #[error(transparent)]
     FormatError(#[from] serde_json::error::Error),
     // #[error("Could not build HTTP request")]
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("timed out waiting for the first authentication token")]
+    AuthTimeout,
+    /// a typed resource that did not match, with the path to the failing field
+    #[error(transparent)]
+    JsonError(#[from] JsonError),
+    #[error(transparent)]
+    InvalidName(#[from] InvalidName),
+    #[error(transparent)]
+    InvalidPath(#[from] InvalidPath),
+    #[error(transparent)]
+    UnexpectedQueryResult(#[from] UnexpectedQueryResult),
+    #[error(transparent)]
+    XmlError(Box<XmlError>),
+    #[error("no authentication token is available")]
+    NotAuthenticated,
+    #[error("SirixDB did not return an ETag for the requested node")]
+    MissingEtag,
+    #[error("the requested database, resource or node does not exist")]
+    NotFound,
+    #[error("not authenticated with SirixDB")]
+    Unauthorized,
+    #[error("not permitted to perform this operation")]
+    Forbidden,
+    #[error("the given ETag does not match the current state of the node")]
+    PreconditionFailed { etag: Option<String> },
+    #[error("the request conflicts with the current state of the resource: {body}")]
+    Conflict { body: String },
+    /// any other 4xx status, such as a malformed request
+    #[error("SirixDB rejected the request with status {status}: {body}")]
+    ClientError { status: u16, body: String },
+    #[error("SirixDB responded with status {status}: {body}")]
+    ServerError { status: u16, body: String },
+}
+
+impl SirixError {
+    /// Map an unsuccessful HTTP response to the matching error.
+    pub fn from_status(status: u16, etag: Option<String>, body: String) -> Self {
+        match status {
+            401 => SirixError::Unauthorized,
+            403 => SirixError::Forbidden,
+            404 => SirixError::NotFound,
+            409 => SirixError::Conflict { body },
+            412 => SirixError::PreconditionFailed { etag },
+            400..=499 => SirixError::ClientError { status, body },
+            _ => SirixError::ServerError { status, body },
+        }
+    }
+}
+
+// boxed, as ureq::Error carries the whole response and would make every `SirixResult` large
+impl From<ureq::Error> for SirixError {
+    fn from(err: ureq::Error) -> Self {
+        SirixError::ConnectionError(Box::new(err))
+    }
+}
+
+impl From<XmlError> for SirixError {
+    fn from(err: XmlError) -> Self {
+        SirixError::XmlError(Box::new(err))
+    }
 }
 
 pub type SirixResult<T> = std::result::Result<T, SirixError>;
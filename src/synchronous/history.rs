@@ -0,0 +1,239 @@
+//! Paging through the history of a resource
+
+use super::super::timestamp::Timestamp;
+use super::super::types::{Commit, HistoryQuery, TwoRevisions};
+use super::super::utils::{
+    history_in_range, history_next_newest, history_page_query, history_reaches,
+};
+use super::error::SirixResult;
+use super::resource::Resource;
+use std::collections::VecDeque;
+
+/// Iterates over the commits of a resource, newest first, fetching `page_size` commits per request.
+///
+/// Pages are requested lazily, so only a single page is held in memory at a time.
+/// Pages are requested by revision number, since `/history` cannot select commits
+/// by timestamp. A timestamp range is applied to each page instead, and paging
+/// stops at the first commit before it.
+#[derive(Debug)]
+pub struct HistoryPager<T> {
+    resource: Resource<T>,
+    range: Option<TwoRevisions>,
+    page_size: u64,
+    remaining: Option<u64>,
+    /// the newest revision of the next page, unknown until the first page of an open range
+    newest: Option<u64>,
+    /// the oldest revision of the range
+    oldest: u64,
+    /// the start of a timestamp range
+    since: Option<Timestamp>,
+    buffer: VecDeque<Commit>,
+    done: bool,
+}
+
+impl<T> HistoryPager<T> {
+    pub fn new(resource: Resource<T>, query: HistoryQuery, page_size: u64) -> Self {
+        let (newest, oldest) = match &query.range {
+            Some(TwoRevisions::Number(start, end)) => (Some(*end), *start),
+            _ => (None, 0),
+        };
+        let since = match &query.range {
+            Some(TwoRevisions::Timestamp(start, _)) => Some(start),
+            _ => None,
+        }
+        .cloned();
+        Self {
+            resource,
+            page_size: page_size.max(1),
+            remaining: query.limit,
+            newest,
+            oldest,
+            since,
+            buffer: VecDeque::new(),
+            done: query.limit == Some(0) || matches!(newest, Some(newest) if newest < oldest),
+            range: query.range,
+        }
+    }
+
+    fn fetch_page(&mut self) -> SirixResult<()> {
+        // commits outside a timestamp range are dropped, so they don't count towards the limit
+        let size = match (&self.range, self.remaining) {
+            (Some(TwoRevisions::Timestamp(..)), _) | (_, None) => self.page_size,
+            (_, Some(remaining)) => remaining.min(self.page_size),
+        };
+        let query = history_page_query(self.newest, self.oldest, size);
+        let history = self.resource.history_with(query.clone())?.body;
+        self.newest = history_next_newest(&query, &history, self.oldest);
+        let reached = history_reaches(&history, self.since.as_ref());
+        let mut commits = history_in_range(history, self.range.as_ref());
+        if let Some(remaining) = self.remaining.as_mut() {
+            commits.truncate(*remaining as usize);
+            *remaining -= commits.len() as u64;
+        }
+        self.done = self.newest.is_none() || reached || self.remaining == Some(0);
+        self.buffer.extend(commits);
+        Ok(())
+    }
+}
+
+impl<T> Iterator for HistoryPager<T> {
+    type Item = SirixResult<Commit>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(commit) = self.buffer.pop_front() {
+                return Some(Ok(commit));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(err) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::*;
+    use crate::timestamp::parse_timestamp;
+    use mockito::mock;
+
+    /// The history of the given revisions, each committed on the day of May 2019 of the same number
+    fn commits(revisions: &[usize]) -> String {
+        let commits: Vec<(usize, usize)> = revisions
+            .iter()
+            .map(|&revision| (revision, revision))
+            .collect();
+        commits_on(&commits)
+    }
+
+    /// The history of the given revisions, each committed on the given day of May 2019
+    fn commits_on(commits: &[(usize, usize)]) -> String {
+        let commits: Vec<String> = commits
+            .iter()
+            .map(|(revision, day)| {
+                format!(
+                    r#"{{"revisionTimestamp":"2019-05-0{}T00:00:00","revision":{},"author":"admin","commitMessage":"commit {}"}}"#,
+                    day, revision, revision
+                )
+            })
+            .collect();
+        format!(r#"{{"history":[{}]}}"#, commits.join(","))
+    }
+
+    #[test]
+    fn test_history_pager() {
+        let _m = mock("GET", "/database/paged/history?revisions=2")
+            .with_status(200)
+            .with_body(commits(&[4, 5]))
+            .create();
+        let _m2 = mock(
+            "GET",
+            "/database/paged/history?startRevision=2&endRevision=3",
+        )
+        .with_status(200)
+        .with_body(commits(&[3, 2]))
+        .create();
+        let _m3 = mock(
+            "GET",
+            "/database/paged/history?startRevision=1&endRevision=1",
+        )
+        .with_status(200)
+        .with_body(commits(&[1]))
+        .expect(1)
+        .create();
+        let resource = test_mocks::json_resource("paged");
+        let query = HistoryQuery {
+            limit: Some(5),
+            ..HistoryQuery::default()
+        };
+        let commits = resource
+            .history_pager(query, 2)
+            .collect::<SirixResult<Vec<Commit>>>()
+            .unwrap();
+        let revisions: Vec<usize> = commits.iter().map(|commit| commit.revision).collect();
+        assert_eq!(revisions, vec![5, 4, 3, 2, 1]);
+        _m3.assert();
+    }
+
+    #[test]
+    fn test_xml_history_pager_timestamps() {
+        let _m = mock("GET", "/database/paged-xml/history?revisions=2")
+            .with_status(200)
+            .with_body(commits_on(&[(5, 9), (4, 4)]))
+            .create();
+        let _m2 = mock(
+            "GET",
+            "/database/paged-xml/history?startRevision=2&endRevision=3",
+        )
+        .with_status(200)
+        .with_body(commits(&[3, 2]))
+        .create();
+        // paging stops at the first commit before the range
+        let _m3 = mock(
+            "GET",
+            "/database/paged-xml/history?startRevision=0&endRevision=1",
+        )
+        .with_status(200)
+        .with_body(commits_on(&[(1, 1), (0, 1)]))
+        .expect(1)
+        .create();
+        let resource = test_mocks::xml_resource("paged-xml");
+        let query = HistoryQuery {
+            range: Some(TwoRevisions::Timestamp(
+                parse_timestamp("2019-05-02T00:00:00").unwrap(),
+                parse_timestamp("2019-05-08T00:00:00").unwrap(),
+            )),
+            limit: None,
+        };
+        let commits = resource
+            .history_pager(query, 2)
+            .collect::<SirixResult<Vec<Commit>>>()
+            .unwrap();
+        let revisions: Vec<usize> = commits.iter().map(|commit| commit.revision).collect();
+        assert_eq!(revisions, vec![4, 3, 2]);
+        _m3.assert();
+    }
+
+    #[test]
+    fn test_history_pager_shared_timestamp() {
+        let _m = mock("GET", "/database/paged-shared/history?revisions=2")
+            .with_status(200)
+            .with_body(commits_on(&[(6, 5), (5, 5)]))
+            .create();
+        let _m2 = mock(
+            "GET",
+            "/database/paged-shared/history?startRevision=3&endRevision=4",
+        )
+        .with_status(200)
+        .with_body(commits_on(&[(4, 5), (3, 3)]))
+        .create();
+        let _m3 = mock(
+            "GET",
+            "/database/paged-shared/history?startRevision=1&endRevision=2",
+        )
+        .with_status(200)
+        .with_body(commits_on(&[(2, 1), (1, 1)]))
+        .expect(1)
+        .create();
+        let resource = test_mocks::json_resource("paged-shared");
+        let query = HistoryQuery {
+            range: Some(TwoRevisions::Timestamp(
+                parse_timestamp("2019-05-02T00:00:00").unwrap(),
+                parse_timestamp("2019-05-09T00:00:00").unwrap(),
+            )),
+            limit: None,
+        };
+        let commits = resource
+            .history_pager(query, 2)
+            .collect::<SirixResult<Vec<Commit>>>()
+            .unwrap();
+        let revisions: Vec<usize> = commits.iter().map(|commit| commit.revision).collect();
+        assert_eq!(revisions, vec![6, 5, 4, 3]);
+        _m3.assert();
+    }
+}
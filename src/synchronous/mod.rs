@@ -1,7 +1,12 @@
 pub mod auth;
+pub mod builder;
 pub mod client;
+pub mod cursor;
 pub mod database;
 pub mod error;
-mod http;
+pub mod history;
+pub mod query;
 pub mod resource;
+pub mod revision;
 pub mod sirix;
+pub mod upload;
@@ -0,0 +1,130 @@
+//! Paging through the result sequence of a query
+
+use super::super::types::Query;
+use super::super::utils::query_result_items;
+use super::error::SirixResult;
+use super::sirix::Sirix;
+use serde_json::Value;
+use std::collections::VecDeque;
+
+/// Iterates over the items of a query result, fetching `page_size` items per request.
+///
+/// Pages are requested lazily, so only a single page is held in memory at a time.
+#[derive(Debug)]
+pub struct QueryPager {
+    sirix: Sirix,
+    query: String,
+    page_size: u128,
+    next_index: u128,
+    buffer: VecDeque<Value>,
+    done: bool,
+}
+
+impl QueryPager {
+    pub fn new(sirix: Sirix, query: &str, page_size: u128) -> Self {
+        Self {
+            sirix,
+            query: query.to_owned(),
+            page_size: page_size.max(1),
+            next_index: 0,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn fetch_page(&mut self) -> SirixResult<()> {
+        let query = Query {
+            start_result_seq_index: Some(self.next_index),
+            end_result_seq_index: Some(self.next_index + self.page_size - 1),
+            query: self.query.clone(),
+        };
+        let items = query_result_items(self.sirix.query(query)?.body)?;
+        if (items.len() as u128) < self.page_size {
+            self.done = true;
+        }
+        self.next_index += self.page_size;
+        self.buffer.extend(items);
+        Ok(())
+    }
+}
+
+impl Iterator for QueryPager {
+    type Item = SirixResult<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.buffer.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.done {
+                return None;
+            }
+            if let Err(err) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::types::UnexpectedQueryResult;
+    use super::super::error::SirixError;
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn test_query_pager() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/")
+            .match_body(Matcher::Json(serde_json::json!({
+                "startResultSeqIndex": 0,
+                "endResultSeqIndex": 1,
+                "query": "1 to 3"
+            })))
+            .with_status(200)
+            .with_body(r#"{"rest":[1,2]}"#)
+            .create();
+        let _m2 = mock("POST", "/")
+            .match_body(Matcher::Json(serde_json::json!({
+                "startResultSeqIndex": 2,
+                "endResultSeqIndex": 3,
+                "query": "1 to 3"
+            })))
+            .with_status(200)
+            .with_body(r#"{"rest":[3]}"#)
+            .expect(1)
+            .create();
+        let sirix = Sirix::new(url.to_owned(), ureq::agent(), None);
+        let items = sirix
+            .query_pager("1 to 3", 2)
+            .collect::<SirixResult<Vec<Value>>>()
+            .unwrap();
+        assert_eq!(items, vec![1, 2, 3]);
+        _m2.assert();
+    }
+
+    #[test]
+    fn test_query_pager_unexpected_result() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/")
+            .match_body(Matcher::Json(serde_json::json!({
+                "startResultSeqIndex": 0,
+                "endResultSeqIndex": 1,
+                "query": "{\"rest\": 1}"
+            })))
+            .with_status(200)
+            .with_body(r#"{"rest":1}"#)
+            .create();
+        let sirix = Sirix::new(url.to_owned(), ureq::agent(), None);
+        let mut pager = sirix.query_pager(r#"{"rest": 1}"#, 2);
+        match pager.next() {
+            Some(Err(SirixError::UnexpectedQueryResult(UnexpectedQueryResult(page)))) => {
+                assert_eq!(page, serde_json::json!({"rest": 1}))
+            }
+            other => panic!("expected UnexpectedQueryResult, got {:?}", other),
+        }
+        assert!(pager.next().is_none());
+    }
+}
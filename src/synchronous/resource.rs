@@ -1,17 +1,32 @@
+use minidom::Element;
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
 
-use crate::types::{DiffArgs, History, MetaNode, MetadataType, ReadArgs, SingleRevision};
+use crate::types::{
+    parse_path, Commit, DiffArgs, DiffResult, History, HistoryQuery, Insert, MetaNode,
+    MetadataType, NodeIdAndEtag, PathSegment, Query, ReadArgs, RevisionArg, SingleRevision,
+    TwoRevisions,
+};
 
+use super::super::endpoints;
 use super::super::info;
-use super::super::types::{DbType, Json, Xml};
-use super::super::utils::build_read_params;
-use super::client::SirixResponse;
-use super::error::SirixResult;
-use super::http::{
-    create_resource, create_resource_string, diff_resource, get_etag, read_resource,
-    read_resource_string, resource_exists, resource_history, resource_history_string,
+use super::super::json;
+use super::super::transport::{SirixRequest, Transport};
+use super::super::types::{DbType, Json, WriteOptions, Xml};
+use super::super::utils::{
+    build_diff_params, build_history_params, build_query_params, build_read_params,
+    build_revision_params, build_write_params, commit_at, revision_history_query, select_history,
 };
+use super::super::xml::{self, parse_element, XmlMetaNode};
+use super::auth::authorization;
+use super::client::{SirixResponse, UreqTransport};
+use super::cursor::ChildrenCursor;
+use super::error::{SirixError, SirixResult};
+use super::history::HistoryPager;
+use super::revision::{RevisionRange, RevisionView};
+use super::upload::ProgressReader;
+use std::io::Read;
 use std::{sync::Arc, sync::RwLock};
 
 ///  Struct for manipulating a resource
@@ -24,187 +39,89 @@ pub struct Resource<T> {
     pub db_type: DbType,
     /// The name of the resource being accessed, or created if it does not yet exist.
     pub resource_name: String,
-    /// the url for the SirixDB server
-    base_uri: String,
-    /// a ureq::Agent
-    agent: ureq::Agent,
+    /// recorded in the commit of each write, see `with_write_options`
+    write_options: WriteOptions,
+    /// sends requests to the SirixDB server
+    transport: UreqTransport,
     /// a reference to authentication data
     auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
 }
 
 impl<T> Resource<T> {
     pub fn create_string(&self, initial_data: String) -> SirixResult<SirixResponse<String>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                create_resource_string(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    &initial_data,
-                )
-            }
-            None => create_resource_string(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                self.db_type.clone(),
-                &self.resource_name,
-                &initial_data,
-            ),
-        }
+        self.transport
+            .send_string(self.create_request()?.body(initial_data))
     }
 
-    pub fn create_raw<U: DeserializeOwned>(
+    /// Create the resource with the initial data read from `reader`,
+    /// without loading it into memory.
+    ///
+    /// `progress` is called with the number of bytes sent so far.
+    /// The resource SirixDB sends back is discarded rather than read into memory.
+    pub fn create_from_reader(
         &self,
-        initial_data: String,
-    ) -> SirixResult<SirixResponse<U>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                create_resource(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    &initial_data,
-                )
-            }
-            None => create_resource(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                self.db_type.clone(),
-                &self.resource_name,
-                &initial_data,
-            ),
-        }
-    }
-
-    pub fn create(&self, initial_data: String) -> SirixResult<SirixResponse<Value>> {
-        self.create_raw(initial_data)
+        reader: impl Read,
+        progress: impl FnMut(u64),
+    ) -> SirixResult<SirixResponse<()>> {
+        self.transport.send_stream(
+            self.create_request()?,
+            Box::new(ProgressReader::new(reader, progress)),
+        )
     }
 
+    /// Whether the resource exists, from the status of a `HEAD` request
     pub fn exists(&self) -> SirixResult<SirixResponse<bool>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                resource_exists(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                )
-            }
-            None => resource_exists(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                self.db_type.clone(),
-                &self.resource_name,
-            ),
+        let response = self.transport.send_no_content(
+            endpoints::resource_exists(&self.db_name, &self.db_type, &self.resource_name)?
+                .authorization(authorization(&self.auth_lock)?),
+        );
+        match response {
+            Ok(response) => Ok(SirixResponse {
+                status: response.status,
+                etag: response.etag,
+                body: true,
+            }),
+            Err(SirixError::NotFound) => Ok(SirixResponse {
+                status: 404,
+                etag: None,
+                body: false,
+            }),
+            Err(err) => Err(err),
         }
     }
 
-    pub fn etag(&self, node_id: u128) -> SirixResult<SirixResponse<()>> {
-        let response = match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                get_etag(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    node_id,
-                )
-            }
-            None => get_etag(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                self.db_type.clone(),
-                &self.resource_name,
-                node_id,
-            ),
-        };
-        return response;
-    }
-
-    pub fn read<U: DeserializeOwned>(
-        &self,
-        read_args: ReadArgs,
-    ) -> SirixResult<SirixResponse<Value>> {
-        self.read_raw(read_args)
+    /// The ETag of the node with the given id, as the body of the response
+    pub fn etag(&self, node_id: u128) -> SirixResult<SirixResponse<String>> {
+        self.etag_at(node_id, None)
     }
 
-    pub fn read_raw<U: DeserializeOwned>(
+    /// The ETag of the node with the given id in `revision`, or in the latest revision
+    pub(crate) fn etag_at(
         &self,
-        read_args: ReadArgs,
-    ) -> SirixResult<SirixResponse<U>> {
-        let params = build_read_params(read_args);
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                read_resource(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    params,
-                )
-            }
-            None => read_resource(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
+        node_id: u128,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<String>> {
+        let response = self.transport.send_no_content(
+            endpoints::get_etag(
                 &self.db_name,
-                self.db_type.clone(),
+                &self.db_type,
                 &self.resource_name,
-                params,
-            ),
-        }
+                node_id,
+                build_revision_params(revision),
+            )?
+            .authorization(authorization(&self.auth_lock)?),
+        )?;
+        let etag = response.etag.ok_or(SirixError::MissingEtag)?;
+        Ok(SirixResponse {
+            status: response.status,
+            etag: Some(etag.clone()),
+            body: etag,
+        })
     }
 
     pub fn read_string(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<String>> {
-        let params = build_read_params(read_args);
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                read_resource_string(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    params,
-                )
-            }
-            None => read_resource_string(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                self.db_type.clone(),
-                &self.resource_name,
-                params,
-            ),
-        }
+        self.transport
+            .send_string(self.read_request(build_read_params(read_args))?)
     }
 
     pub fn read_with_metadata_string(
@@ -214,69 +131,190 @@ impl<T> Resource<T> {
     ) -> SirixResult<SirixResponse<String>> {
         let mut params = build_read_params(read_args);
         params.push(("withMetadata".to_owned(), meta_type.to_string()));
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                read_resource_string(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    params,
-                )
-            }
-            None => read_resource_string(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
+        self.transport.send_string(self.read_request(params)?)
+    }
+
+    pub fn query_string(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<String>> {
+        self.transport
+            .send_string(self.read_request(build_query_params(query.into(), revision))?)
+    }
+
+    pub fn history_string(&self) -> SirixResult<SirixResponse<String>> {
+        self.transport.send_string(
+            endpoints::resource_history(&self.db_name, &self.resource_name, Vec::new())?
+                .authorization(authorization(&self.auth_lock)?),
+        )
+    }
+
+    pub fn history_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+    ) -> SirixResult<SirixResponse<U>> {
+        self.transport.send_json(
+            endpoints::resource_history(&self.db_name, &self.resource_name, Vec::new())?
+                .authorization(authorization(&self.auth_lock)?),
+        )
+    }
+
+    pub fn history(&self) -> SirixResult<SirixResponse<History>> {
+        self.history_raw()
+    }
+
+    /// The commits selected by `query`, in a single request
+    pub fn history_with(&self, query: HistoryQuery) -> SirixResult<SirixResponse<History>> {
+        let mut response: SirixResponse<History> = self.transport.send_json(
+            endpoints::resource_history(
                 &self.db_name,
-                self.db_type.clone(),
                 &self.resource_name,
-                params,
-            ),
-        }
+                build_history_params(&query),
+            )?
+            .authorization(authorization(&self.auth_lock)?),
+        )?;
+        response.body = select_history(response.body, &query);
+        Ok(response)
     }
 
-    pub fn read_with_metadata_raw<U: DeserializeOwned>(
+    pub fn diff_raw<U: DeserializeOwned + Send + 'static>(
         &self,
-        meta_type: MetadataType,
-        read_args: ReadArgs,
+        args: DiffArgs,
     ) -> SirixResult<SirixResponse<U>> {
-        let mut params = build_read_params(read_args);
-        params.push(("withMetadata".to_owned(), meta_type.to_string()));
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                read_resource(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                    params,
-                )
-            }
-            None => read_resource(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
+        self.transport.send_json(
+            endpoints::diff_resource(&self.db_name, &self.resource_name, build_diff_params(args))?
+                .authorization(authorization(&self.auth_lock)?),
+        )
+    }
+
+    pub fn diff_string(&self, args: DiffArgs) -> SirixResult<SirixResponse<String>> {
+        self.transport.send_string(
+            endpoints::diff_resource(&self.db_name, &self.resource_name, build_diff_params(args))?
+                .authorization(authorization(&self.auth_lock)?),
+        )
+    }
+
+    pub fn diff(&self, args: DiffArgs) -> SirixResult<SirixResponse<DiffResult>> {
+        self.diff_raw(args)
+    }
+
+    /// Insert the data read from `reader` relative to the node with id `node_id`,
+    /// or replace that node, see `create_from_reader`.
+    pub fn update_from_reader(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        reader: impl Read,
+        etag: Option<String>,
+        progress: impl FnMut(u64),
+    ) -> SirixResult<SirixResponse<()>> {
+        self.transport.send_stream(
+            self.update_request(node_id, insert, etag)?,
+            Box::new(ProgressReader::new(reader, progress)),
+        )
+    }
+
+    /// Delete the node with the given id and ETag, or the whole resource
+    pub fn delete(&self, node_and_etag: Option<NodeIdAndEtag>) -> SirixResult<SirixResponse<()>> {
+        self.transport.send_no_content(
+            endpoints::resource_delete(
                 &self.db_name,
-                self.db_type.clone(),
+                &self.db_type,
                 &self.resource_name,
-                params,
-            ),
-        }
+                node_and_etag,
+                build_write_params(&self.write_options),
+            )?
+            .authorization(authorization(&self.auth_lock)?),
+        )
     }
 
-    pub fn read_with_metadata(
+    /// `PUT /<db_name>/<name>`, without the initial data
+    fn create_request(&self) -> SirixResult<SirixRequest> {
+        Ok(endpoints::create_resource(
+            &self.db_name,
+            &self.db_type,
+            &self.resource_name,
+            build_write_params(&self.write_options),
+        )?
+        .authorization(authorization(&self.auth_lock)?))
+    }
+
+    /// `POST /<db_name>/<name>` without the data, fetching the ETag of the node if none is given
+    fn update_request(
         &self,
-        meta_type: MetadataType,
-        read_args: ReadArgs,
-    ) -> SirixResult<SirixResponse<MetaNode>> {
-        self.read_with_metadata_raw(meta_type, read_args)
+        node_id: u128,
+        insert: Insert,
+        etag: Option<String>,
+    ) -> SirixResult<SirixRequest> {
+        let etag = match etag {
+            Some(etag) => etag,
+            None => self.etag(node_id)?.body,
+        };
+        Ok(endpoints::update_resource(
+            &self.db_name,
+            &self.db_type,
+            &self.resource_name,
+            NodeIdAndEtag { node_id, etag },
+            insert,
+            build_write_params(&self.write_options),
+        )?
+        .authorization(authorization(&self.auth_lock)?))
+    }
+
+    /// `GET /<db_name>/<name>` with the given parameters
+    fn read_request(&self, params: Vec<(String, String)>) -> SirixResult<SirixRequest> {
+        Ok(
+            endpoints::read_resource(&self.db_name, &self.db_type, &self.resource_name, params)?
+                .authorization(authorization(&self.auth_lock)?),
+        )
+    }
+}
+
+impl<T: Clone> Resource<T> {
+    /// A handle to the same resource whose writes record `options` in their commit
+    pub fn with_write_options(&self, options: WriteOptions) -> Self {
+        Self {
+            write_options: options,
+            ..self.clone()
+        }
+    }
+
+    /// Lazily iterate over the commits selected by `query`, newest first,
+    /// `page_size` commits per request.
+    pub fn history_pager(&self, query: HistoryQuery, page_size: u64) -> HistoryPager<T> {
+        HistoryPager::new(self.clone(), query, page_size)
+    }
+
+    /// The resource as it was at `revision`, resolved to a revision number.
+    ///
+    /// For a timestamp, this is the latest revision committed at or before it.
+    /// Returns `SirixError::NotFound` if there is no such revision.
+    pub fn at(&self, revision: SingleRevision) -> SirixResult<RevisionView<T>> {
+        let commit = self.resolve_revision(revision)?;
+        Ok(RevisionView::new(Arc::new(self.clone()), commit))
+    }
+
+    /// The revisions from the first to the second of `revisions`, see `at`.
+    pub fn between(&self, revisions: TwoRevisions) -> SirixResult<RevisionRange<T>> {
+        let (first, last) = match revisions {
+            TwoRevisions::Number(first, last) => {
+                (SingleRevision::Number(first), SingleRevision::Number(last))
+            }
+            TwoRevisions::Timestamp(first, last) => (
+                SingleRevision::Timestamp(first),
+                SingleRevision::Timestamp(last),
+            ),
+        };
+        let resource = Arc::new(self.clone());
+        Ok(RevisionRange {
+            first: RevisionView::new(resource.clone(), self.resolve_revision(first)?),
+            last: RevisionView::new(resource, self.resolve_revision(last)?),
+        })
+    }
+
+    fn resolve_revision(&self, revision: SingleRevision) -> SirixResult<Commit> {
+        let history = self.history_with(revision_history_query(&revision))?.body;
+        commit_at(history, &revision).ok_or(SirixError::NotFound)
     }
 }
 
@@ -287,118 +325,212 @@ impl Resource<Json> {
         base_uri: String,
         agent: ureq::Agent,
         auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
+    ) -> Self {
+        Self::with_transport(
+            db_name,
+            resource_name,
+            UreqTransport::new(agent, &base_uri),
+            auth_lock,
+        )
+    }
+
+    pub(crate) fn with_transport(
+        db_name: String,
+        resource_name: String,
+        transport: UreqTransport,
+        auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
     ) -> Self {
         Self {
             _t: Json,
             db_name,
             db_type: DbType::Json(Json),
             resource_name,
-            base_uri,
-            agent,
+            transport,
+            write_options: WriteOptions::default(),
             auth_lock,
         }
     }
 
-    pub fn history_string(&self) -> SirixResult<SirixResponse<String>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                resource_history_string(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                )
-            }
-            None => resource_history_string(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                self.db_type.clone(),
-                &self.resource_name,
-            ),
-        }
+    pub fn create_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        initial_data: String,
+    ) -> SirixResult<SirixResponse<U>> {
+        self.transport
+            .send_json(self.create_request()?.body(initial_data))
     }
 
-    pub fn history_raw<U: DeserializeOwned>(&self) -> SirixResult<SirixResponse<U>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                resource_history(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    self.db_type.clone(),
-                    &self.resource_name,
-                )
-            }
-            None => resource_history(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                self.db_type.clone(),
-                &self.resource_name,
-            ),
-        }
+    pub fn create(&self, initial_data: String) -> SirixResult<SirixResponse<Value>> {
+        self.create_raw(initial_data)
     }
 
-    pub fn history(&self) -> SirixResult<SirixResponse<History>> {
-        self.history_raw()
+    /// Create the resource with `value` serialized as JSON
+    pub fn create_from<V: Serialize + ?Sized>(
+        &self,
+        value: &V,
+    ) -> SirixResult<SirixResponse<Value>> {
+        self.create(json::serialize(value)?)
     }
 
-    // TODO fix return type
-    pub fn diff(&self, args: DiffArgs) -> SirixResult<SirixResponse<()>> {
-        let mut params: Vec<(String, String)> = Vec::new();
-        match args.node_id {
-            Some(node_id) => params.push(("startNodeKey".to_owned(), node_id.to_string())),
-            None => (),
-        }
-        match args.max_depth {
-            Some(max_depth) => params.push(("maxDepth".to_owned(), max_depth.to_string())),
-            None => (),
-        }
-        match args.first_revision {
-            SingleRevision::Timestamp(revision) => {
-                params.push(("first-revision".to_owned(), revision))
-            }
-            SingleRevision::Number(revision) => {
-                params.push(("first-revision".to_owned(), revision.to_string()))
-            }
-        }
-        match args.second_revision {
-            SingleRevision::Timestamp(revision) => {
-                params.push(("second-revision".to_owned(), revision))
-            }
-            SingleRevision::Number(revision) => {
-                params.push(("second-revision".to_owned(), revision.to_string()))
-            }
+    pub fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Value>> {
+        self.read_raw(read_args)
+    }
+
+    /// Read the resource into `U`.
+    ///
+    /// Unlike `read_raw`, errors report the path of the field that did not match.
+    pub fn read_as<U: DeserializeOwned>(
+        &self,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.read_string(read_args)?, json::deserialize)
+    }
+
+    pub fn read_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<U>> {
+        self.transport
+            .send_json(self.read_request(build_read_params(read_args))?)
+    }
+
+    pub fn read_with_metadata_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<U>> {
+        let mut params = build_read_params(read_args);
+        params.push(("withMetadata".to_owned(), meta_type.to_string()));
+        self.transport.send_json(self.read_request(params)?)
+    }
+
+    pub fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<MetaNode>> {
+        self.read_with_metadata_raw(meta_type, read_args)
+    }
+
+    /// Lazily iterate over the children of the array or object with id `node_id`,
+    /// or of the top-level value, `page_size` children per request.
+    pub fn children_cursor(
+        &self,
+        node_id: Option<u128>,
+        page_size: u64,
+        revision: Option<SingleRevision>,
+    ) -> ChildrenCursor {
+        ChildrenCursor::new(self.clone(), node_id, page_size, revision)
+    }
+
+    /// Insert `data` relative to the node with id `node_id`, or replace that node.
+    ///
+    /// If no `etag` is given, the current ETag of the node is fetched first.
+    pub fn update_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<U>> {
+        self.transport
+            .send_json(self.update_request(node_id, insert, etag)?.body(data))
+    }
+
+    pub fn update(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<Value>> {
+        self.update_raw(node_id, insert, data, etag)
+    }
+
+    /// Like `update`, with `value` serialized as JSON
+    pub fn update_from<V: Serialize + ?Sized>(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        value: &V,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<Value>> {
+        self.update(node_id, insert, json::serialize(value)?, etag)
+    }
+
+    /// Find the node at `path` in the latest revision, and fetch its ETag.
+    ///
+    /// `path` is a JSON Pointer such as `/a/b/3/c`, or of the form `a.b[3].c`.
+    /// Returns `SirixError::NotFound` if there is no node at `path`.
+    pub fn resolve(&self, path: &str) -> SirixResult<NodeIdAndEtag> {
+        self.resolve_with(path, MetaNode::at_path)
+    }
+
+    /// Replace the node at `path` with `value`, see `resolve`.
+    pub fn set<V: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        value: &V,
+    ) -> SirixResult<SirixResponse<Value>> {
+        let data = json::serialize(value)?;
+        let node = self.resolve(path)?;
+        self.update(node.node_id, Insert::Replace, data, Some(node.etag))
+    }
+
+    /// Delete the node at `path`, see `resolve`.
+    ///
+    /// For the member of an object, both the key and its value are deleted.
+    pub fn remove(&self, path: &str) -> SirixResult<SirixResponse<()>> {
+        let node = self.resolve_with(path, MetaNode::member_at_path)?;
+        self.delete(Some(node))
+    }
+
+    fn resolve_with(
+        &self,
+        path: &str,
+        find: for<'a, 'b> fn(&'a MetaNode, &'b [PathSegment]) -> Option<&'a MetaNode>,
+    ) -> SirixResult<NodeIdAndEtag> {
+        let path = parse_path(path)?;
+        // only read as deep as the path goes, where object keys are a level
+        // between an object and the values of its members
+        let read_args = ReadArgs {
+            max_level: Some(2 * path.len() as u64 + 1),
+            ..ReadArgs::default()
         };
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                diff_resource(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                    &self.db_name,
-                    &self.resource_name,
-                    params,
-                )
-            }
-            None => diff_resource(
-                self.agent.clone(),
-                None,
-                &self.base_uri,
-                &self.db_name,
-                &self.resource_name,
-                params,
-            ),
-        }
+        let root = self.read_with_metadata(MetadataType::Key, read_args)?.body;
+        let node_id = find(&root, &path).ok_or(SirixError::NotFound)?.node_key() as u128;
+        let etag = self.etag(node_id)?.body;
+        Ok(NodeIdAndEtag { node_id, etag })
+    }
+
+    /// Run a JSONiq query with this resource as its context item.
+    ///
+    /// Accepts a query string, or a `Query` to fetch only part of the result sequence.
+    pub fn query(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<Value>> {
+        self.query_raw(query, revision)
+    }
+
+    pub fn query_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<U>> {
+        self.transport
+            .send_json(self.read_request(build_query_params(query.into(), revision))?)
+    }
+
+    /// Run a query, and deserialize its result into `U`.
+    ///
+    /// Errors report the path of the field that did not match.
+    pub fn query_as<U: DeserializeOwned>(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.query_string(query, revision)?, json::deserialize)
     }
 }
 
@@ -409,15 +541,593 @@ impl Resource<Xml> {
         base_uri: String,
         agent: ureq::Agent,
         auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
+    ) -> Self {
+        Self::with_transport(
+            db_name,
+            resource_name,
+            UreqTransport::new(agent, &base_uri),
+            auth_lock,
+        )
+    }
+
+    pub(crate) fn with_transport(
+        db_name: String,
+        resource_name: String,
+        transport: UreqTransport,
+        auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
     ) -> Self {
         Self {
             _t: Xml,
             db_name,
             db_type: DbType::XML(Xml),
             resource_name,
-            base_uri,
-            agent,
+            transport,
+            write_options: WriteOptions::default(),
             auth_lock,
         }
     }
+
+    pub fn create(&self, initial_data: String) -> SirixResult<SirixResponse<Element>> {
+        parse_body(self.create_string(initial_data)?, parse_element)
+    }
+
+    /// Create the resource, and deserialize the response with quick-xml
+    pub fn create_raw<U: DeserializeOwned>(
+        &self,
+        initial_data: String,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.create_string(initial_data)?, xml::deserialize)
+    }
+
+    pub fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Element>> {
+        parse_body(self.read_string(read_args)?, parse_element)
+    }
+
+    /// Read the resource, and deserialize the response with quick-xml
+    pub fn read_raw<U: DeserializeOwned>(
+        &self,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.read_string(read_args)?, xml::deserialize)
+    }
+
+    /// Read the resource with the `rest:` attributes SirixDB adds for `meta_type`.
+    ///
+    /// Returns the outermost elements with metadata.
+    pub fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<Vec<XmlMetaNode>>> {
+        parse_body(
+            self.read_with_metadata_string(meta_type, read_args)?,
+            |body| XmlMetaNode::collect(&parse_element(body)?),
+        )
+    }
+
+    /// Run an XQuery with this resource as its context item.
+    ///
+    /// SirixDB wraps the result sequence in a `rest:sequence` element.
+    pub fn query(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<Element>> {
+        parse_body(self.query_string(query, revision)?, parse_element)
+    }
+
+    /// Run a query, and deserialize its result with quick-xml
+    pub fn query_raw<U: DeserializeOwned>(
+        &self,
+        query: impl Into<Query>,
+        revision: Option<RevisionArg>,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(self.query_string(query, revision)?, xml::deserialize)
+    }
+
+    pub fn update_string(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<String>> {
+        self.transport
+            .send_string(self.update_request(node_id, insert, etag)?.body(data))
+    }
+
+    /// Insert `data` relative to the node with id `node_id`, or replace that node.
+    ///
+    /// If no `etag` is given, the current ETag of the node is fetched first.
+    pub fn update(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<Element>> {
+        parse_body(
+            self.update_string(node_id, insert, data, etag)?,
+            parse_element,
+        )
+    }
+
+    /// Update the resource, and deserialize the response with quick-xml
+    pub fn update_raw<U: DeserializeOwned>(
+        &self,
+        node_id: u128,
+        insert: Insert,
+        data: String,
+        etag: Option<String>,
+    ) -> SirixResult<SirixResponse<U>> {
+        parse_body(
+            self.update_string(node_id, insert, data, etag)?,
+            xml::deserialize,
+        )
+    }
+}
+
+/// Parse the body of a response read as text
+pub(crate) fn parse_body<U, E: Into<SirixError>>(
+    response: SirixResponse<String>,
+    parse: impl FnOnce(&str) -> Result<U, E>,
+) -> SirixResult<SirixResponse<U>> {
+    Ok(SirixResponse {
+        body: parse(&response.body).map_err(Into::into)?,
+        status: response.status,
+        etag: response.etag,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::*;
+    use crate::timestamp::parse_timestamp;
+    use crate::types::SingleRevision;
+    use mockito::{mock, Matcher};
+
+    const DIFF_BODY: &str = r#"{"database":"database","resource":"resource","old-revision":1,"new-revision":2,"diffs":[]}"#;
+
+    #[test]
+    fn test_update_fetches_etag() {
+        let _m = mock("HEAD", "/database/resource?nodeId=2")
+            .with_status(200)
+            .with_header("etag", "abc123")
+            .create();
+        let _m2 = mock("POST", "/database/resource?nodeId=2&insert=asFirstChild")
+            .match_header("etag", "abc123")
+            .match_header("content-type", "application/json")
+            .match_body(r#"{"key":"value"}"#)
+            .with_status(200)
+            .with_body(r#"{"key":"value"}"#)
+            .create();
+        let resource = test_mocks::json_resource("resource");
+        let response = resource
+            .update(2, Insert::Child, r#"{"key":"value"}"#.to_owned(), None)
+            .unwrap();
+        assert_eq!(response.body, serde_json::json!({"key": "value"}));
+    }
+
+    #[test]
+    fn test_diff_url() {
+        let _m = mock(
+            "GET",
+            "/database/resource/diff?startNodeKey=3&maxDepth=2&first-revision=1&second-revision=2",
+        )
+        .with_status(200)
+        .with_body(DIFF_BODY)
+        .create();
+        let resource = test_mocks::json_resource("resource");
+        let response = resource
+            .diff(DiffArgs {
+                first_revision: SingleRevision::Number(1),
+                second_revision: SingleRevision::Number(2),
+                node_id: Some(3),
+                max_depth: Some(2),
+            })
+            .unwrap();
+        assert_eq!(response.body.old_revision, 1);
+        assert_eq!(response.body.new_revision, 2);
+    }
+
+    #[test]
+    fn test_diff_url_with_timestamps() {
+        let _m = mock("GET", "/database/resource/diff")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("first-revision".into(), "2019-05-01T00:00:00".into()),
+                Matcher::UrlEncoded("second-revision".into(), "2019-05-02T00:00:00".into()),
+            ]))
+            .with_status(200)
+            .with_body(DIFF_BODY)
+            .create();
+        let resource = test_mocks::json_resource("resource");
+        let response = resource.diff_string(DiffArgs {
+            first_revision: SingleRevision::Timestamp(
+                parse_timestamp("2019-05-01T00:00:00").unwrap(),
+            ),
+            second_revision: SingleRevision::Timestamp(
+                parse_timestamp("2019-05-02T00:00:00").unwrap(),
+            ),
+            node_id: None,
+            max_depth: None,
+        });
+        assert_eq!(response.unwrap().body, DIFF_BODY);
+    }
+
+    #[test]
+    fn test_query_with_revision() {
+        let _m = mock("GET", "/database/resource")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), "for $i in .[] return $i".into()),
+                Matcher::UrlEncoded("startResultSeqIndex".into(), "2".into()),
+                Matcher::UrlEncoded("revision".into(), "3".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"rest":["a","b"]}"#)
+            .create();
+        let resource = test_mocks::json_resource("resource");
+        let response = resource
+            .query(
+                Query {
+                    start_result_seq_index: Some(2),
+                    ..Query::new("for $i in .[] return $i")
+                },
+                Some(RevisionArg::SingleRevision(SingleRevision::Number(3))),
+            )
+            .unwrap();
+        assert_eq!(response.body, serde_json::json!({"rest": ["a", "b"]}));
+    }
+
+    #[test]
+    fn test_read_with_metadata() {
+        let _m = mock("GET", "/database/resource")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("withMetadata".into(), "nodeKey".into()),
+                Matcher::UrlEncoded("revision".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(include_str!("../../tests/resources/metadata/node_key.json"))
+            .create();
+        let resource = test_mocks::json_resource("resource");
+        let response = resource
+            .read_with_metadata(
+                MetadataType::Key,
+                ReadArgs {
+                    node_id: None,
+                    revision: Some(RevisionArg::SingleRevision(SingleRevision::Number(1))),
+                    max_level: None,
+                    top_level_limit: None,
+                    top_level_skip_last_node: None,
+                },
+            )
+            .unwrap();
+        match response.body {
+            MetaNode::Object(object) => {
+                assert_eq!(object.metadata.node_key, 1);
+                assert_eq!(object.value.len(), 4);
+            }
+            node => panic!("expected object, got {:?}", node),
+        }
+    }
+
+    #[test]
+    fn test_set_path() {
+        let _m = mock("GET", "/database/resource?maxLevel=5&withMetadata=nodeKey")
+            .with_status(200)
+            .with_body(include_str!("../../tests/resources/metadata/all.json"))
+            .create();
+        let _m2 = mock("HEAD", "/database/resource?nodeId=5")
+            .with_status(200)
+            .with_header("etag", "def456")
+            .create();
+        let _m3 = mock("POST", "/database/resource?nodeId=5&insert=replace")
+            .match_header("etag", "def456")
+            .match_body(r#"{"qux":1}"#)
+            .with_status(200)
+            .with_body(r#"{"foo":["bar",{"qux":1},2.33]}"#)
+            .create();
+        let resource = test_mocks::json_resource("resource");
+        let response = resource
+            .set("foo[1]", &serde_json::json!({"qux": 1}))
+            .unwrap();
+        assert_eq!(
+            response.body,
+            serde_json::json!({"foo": ["bar", {"qux": 1}, 2.33]})
+        );
+    }
+
+    #[test]
+    fn test_resolve_missing_path() {
+        let _m = mock("GET", "/database/resource?maxLevel=5&withMetadata=nodeKey")
+            .with_status(200)
+            .with_body(include_str!("../../tests/resources/metadata/all.json"))
+            .create();
+        let resource = test_mocks::json_resource("resource");
+        assert!(matches!(
+            resource.resolve("/foo/7"),
+            Err(SirixError::NotFound)
+        ));
+        assert!(matches!(
+            resource.resolve("foo..bar"),
+            Err(SirixError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            resource.set("/foo/7", "baz"),
+            Err(SirixError::NotFound)
+        ));
+    }
+
+    #[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+    struct Book {
+        title: String,
+        year: u16,
+    }
+
+    #[test]
+    fn test_create_from() {
+        let _m = mock("PUT", "/database/typed")
+            .match_header("content-type", "application/json")
+            .match_body(r#"[{"title":"Dune","year":1965}]"#)
+            .with_status(200)
+            .with_body(r#"[{"title":"Dune","year":1965}]"#)
+            .create();
+        let resource = test_mocks::json_resource("typed");
+        let books = vec![Book {
+            title: "Dune".to_owned(),
+            year: 1965,
+        }];
+        let response = resource.create_from(&books).unwrap();
+        assert_eq!(response.body, serde_json::to_value(&books).unwrap());
+    }
+
+    #[test]
+    fn test_read_as() {
+        let _m = mock("GET", "/database/typed?revision=1")
+            .with_status(200)
+            .with_body(r#"[{"title":"Dune","year":1965}]"#)
+            .create();
+        let _m2 = mock("GET", "/database/typed?revision=2")
+            .with_status(200)
+            .with_body(r#"[{"title":"Dune","year":1965},{"title":"Emma"}]"#)
+            .create();
+        let resource = test_mocks::json_resource("typed");
+        let revision = |revision| ReadArgs {
+            revision: Some(RevisionArg::SingleRevision(SingleRevision::Number(
+                revision,
+            ))),
+            ..ReadArgs::default()
+        };
+        let books: Vec<Book> = resource.read_as(revision(1)).unwrap().body;
+        assert_eq!(books[0].year, 1965);
+        match resource.read_as::<Vec<Book>>(revision(2)) {
+            Err(SirixError::JsonError(error)) => assert_eq!(error.path().to_string(), "[1]"),
+            response => panic!("expected a JSON error, got {:?}", response),
+        }
+    }
+
+    #[test]
+    fn test_exists() {
+        let _m = mock("HEAD", "/database/present").with_status(200).create();
+        let _m2 = mock("HEAD", "/database/absent").with_status(404).create();
+        let resource = |name: &str| test_mocks::json_resource(name);
+        assert!(resource("present").exists().unwrap().body);
+        assert!(!resource("absent").exists().unwrap().body);
+    }
+
+    #[test]
+    fn test_create_from_reader() {
+        let _m = mock("PUT", "/database/upload?commitMessage=import")
+            .match_header("content-type", "application/json")
+            .match_body(r#"[{"title":"Dune","year":1965}]"#)
+            .with_status(200)
+            .with_body(r#"[{"title":"Dune","year":1965}]"#)
+            .create();
+        let resource = test_mocks::json_resource("upload");
+        let data = r#"[{"title":"Dune","year":1965}]"#;
+        let mut sent = 0;
+        let response = resource
+            .with_write_options(WriteOptions::message("import"))
+            .create_from_reader(data.as_bytes(), |bytes| sent = bytes)
+            .unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(sent, data.len() as u64);
+    }
+
+    #[test]
+    fn test_write_options_history() {
+        let m = mock(
+            "POST",
+            "/database/commits?nodeId=1&insert=asFirstChild&commitMessage=add+a+book&commitTimestamp=2019-05-01T12%3A30%3A00",
+        )
+        .match_header("etag", "abc123")
+            .with_status(200)
+            .with_body(r#"["Dune"]"#)
+            .create();
+        let _m2 = mock("GET", "/database/commits/history")
+            .with_status(200)
+            .with_body(
+                r#"{"history":[{"revisionTimestamp":"2019-05-01T12:30:00","revision":1,"author":"admin","commitMessage":"add a book"}]}"#,
+            )
+            .create();
+        let resource = test_mocks::json_resource("commits");
+        let options = WriteOptions {
+            commit_timestamp: Some(parse_timestamp("2019-05-01T12:30:00").unwrap()),
+            ..WriteOptions::message("add a book")
+        };
+        resource
+            .with_write_options(options.clone())
+            .update(
+                1,
+                Insert::Child,
+                r#""Dune""#.to_owned(),
+                Some("abc123".to_owned()),
+            )
+            .unwrap();
+        m.assert();
+        let history = resource.history().unwrap().body;
+        assert_eq!(
+            Some(&history[0].commit_message),
+            options.commit_message.as_ref()
+        );
+        assert_eq!(
+            Some(&history[0].revision_timestamp),
+            options.commit_timestamp.as_ref()
+        );
+    }
+
+    #[test]
+    fn test_xml_read() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Book {
+            lang: String,
+            title: String,
+        }
+
+        let _m = mock("GET", "/database/books?nodeId=2")
+            .match_header("accept", "application/xml")
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body(r#"<book lang="en"><title>Dune</title></book>"#)
+            .create();
+        let resource = test_mocks::xml_resource("books");
+        let read_args = || ReadArgs {
+            node_id: Some(2),
+            ..ReadArgs::default()
+        };
+        let element = resource.read(read_args()).unwrap().body;
+        assert_eq!(element.name(), "book");
+        assert_eq!(element.attr("lang"), Some("en"));
+        let book: Book = resource.read_raw(read_args()).unwrap().body;
+        assert_eq!(book.title, "Dune");
+    }
+
+    #[test]
+    fn test_xml_query() {
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Sequence {
+            item: Vec<Item>,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Item {
+            book: Book,
+        }
+
+        #[derive(Debug, serde::Deserialize, PartialEq)]
+        struct Book {
+            lang: String,
+        }
+
+        let _m = mock("GET", "/database/books?query=%2F%2Fbook&revision=2")
+            .match_header("accept", "application/xml")
+            .with_status(200)
+            .with_header("content-type", "application/xml")
+            .with_body(
+                r#"<rest:sequence xmlns:rest="https://sirix.io"><rest:item><book lang="en"/></rest:item><rest:item><book lang="de"/></rest:item></rest:sequence>"#,
+            )
+            .create();
+        let resource = test_mocks::xml_resource("books");
+        let revision = || Some(RevisionArg::SingleRevision(SingleRevision::Number(2)));
+        let sequence = resource.query("//book", revision()).unwrap().body;
+        assert_eq!(sequence.name(), "sequence");
+        assert_eq!(sequence.children().count(), 2);
+        let sequence: Sequence = resource.query_raw("//book", revision()).unwrap().body;
+        let langs: Vec<&str> = sequence
+            .item
+            .iter()
+            .map(|item| item.book.lang.as_str())
+            .collect();
+        assert_eq!(langs, vec!["en", "de"]);
+    }
+
+    #[test]
+    fn test_xml_update() {
+        let _m = mock("HEAD", "/database/books?nodeId=3")
+            .with_status(200)
+            .with_header("etag", "xyz")
+            .create();
+        let _m2 = mock("POST", "/database/books?nodeId=3&insert=asRightSibling")
+            .match_header("etag", "xyz")
+            .match_header("content-type", "application/xml")
+            .match_body("<book/>")
+            .with_status(200)
+            .with_body("<shop><book/><book/></shop>")
+            .create();
+        let response = test_mocks::xml_resource("books")
+            .update(3, Insert::Right, "<book/>".to_owned(), None)
+            .unwrap();
+        assert_eq!(response.body.children().count(), 2);
+    }
+
+    #[test]
+    fn test_xml_history() {
+        let _m = mock("GET", "/database/books/history")
+            .match_header("accept", "application/json")
+            .with_status(200)
+            .with_body(r#"{"history":[]}"#)
+            .create();
+        assert!(test_mocks::xml_resource("books")
+            .history()
+            .unwrap()
+            .body
+            .is_empty());
+    }
+
+    #[test]
+    fn test_xml_invalid() {
+        let _m = mock("GET", "/database/books?nodeId=4")
+            .with_status(200)
+            .with_body("<book>")
+            .create();
+        let response = test_mocks::xml_resource("books").read(ReadArgs {
+            node_id: Some(4),
+            ..ReadArgs::default()
+        });
+        assert!(matches!(response, Err(SirixError::XmlError(_))));
+    }
+
+    #[test]
+    fn test_encoded_resource_name() {
+        let url = &mockito::server_url();
+        let _m = mock("GET", "/my%20database/resource%3F/history")
+            .with_status(200)
+            .with_body(r#"{"history":[]}"#)
+            .create();
+        let resource = Resource::<Json>::new(
+            "my database".to_owned(),
+            "resource?".to_owned(),
+            url.to_owned(),
+            ureq::agent(),
+            None,
+        );
+        assert_eq!(resource.history_string().unwrap().body, r#"{"history":[]}"#);
+    }
+
+    #[test]
+    fn test_invalid_name() {
+        let resource = test_mocks::json_resource("../resource");
+        let response = resource.history_string();
+        assert!(matches!(response, Err(SirixError::InvalidName(_))));
+    }
+
+    #[test]
+    fn test_not_authenticated() {
+        let url = &mockito::server_url();
+        let resource = Resource::<Json>::new(
+            "database".to_owned(),
+            "resource".to_owned(),
+            url.to_owned(),
+            ureq::agent(),
+            Some(Arc::new(RwLock::new(None))),
+        );
+        let response = resource.read_string(ReadArgs {
+            node_id: None,
+            revision: None,
+            max_level: None,
+            top_level_limit: None,
+            top_level_skip_last_node: None,
+        });
+        assert!(matches!(response, Err(SirixError::NotAuthenticated)));
+    }
 }
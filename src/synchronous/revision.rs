@@ -0,0 +1,423 @@
+//! Reading a resource as it was at past revisions
+
+use super::super::timestamp::Timestamp;
+use super::super::types::{
+    Commit, DiffArgs, DiffResult, History, HistoryQuery, Json, MetaNode, MetadataType, Query,
+    ReadArgs, RevisionArg, SingleRevision, TwoRevisions, Xml,
+};
+use super::super::xml::{parse_element, XmlMetaNode};
+use super::client::SirixResponse;
+use super::error::SirixResult;
+use super::resource::{parse_body, Resource};
+use minidom::Element;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// A resource pinned to a single revision, created by `Resource::at`.
+///
+/// Clones share the resource, so views are cheap to pass around.
+/// The revision of `ReadArgs` passed to a view is ignored.
+#[derive(Debug, Clone)]
+pub struct RevisionView<T> {
+    resource: Arc<Resource<T>>,
+    /// the number of the revision
+    pub revision: u64,
+    /// when the revision was committed
+    pub timestamp: Timestamp,
+}
+
+impl<T> RevisionView<T> {
+    pub(crate) fn new(resource: Arc<Resource<T>>, commit: Commit) -> Self {
+        Self {
+            resource,
+            revision: commit.revision as u64,
+            timestamp: commit.revision_timestamp,
+        }
+    }
+
+    fn revision_arg(&self) -> RevisionArg {
+        RevisionArg::SingleRevision(SingleRevision::Number(self.revision))
+    }
+
+    fn read_args(&self, read_args: ReadArgs) -> ReadArgs {
+        ReadArgs {
+            revision: Some(self.revision_arg()),
+            ..read_args
+        }
+    }
+
+    /// The ETag of the node with the given id in this revision
+    pub fn etag(&self, node_id: u128) -> SirixResult<SirixResponse<String>> {
+        self.resource.etag_at(node_id, Some(self.revision_arg()))
+    }
+
+    /// The changes from this revision to `other`
+    pub fn diff_to(&self, other: &RevisionView<T>) -> SirixResult<SirixResponse<DiffResult>> {
+        self.resource.diff(DiffArgs {
+            first_revision: SingleRevision::Number(self.revision),
+            second_revision: SingleRevision::Number(other.revision),
+            node_id: None,
+            max_depth: None,
+        })
+    }
+}
+
+impl RevisionView<Json> {
+    pub fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Value>> {
+        self.resource.read(self.read_args(read_args))
+    }
+
+    pub fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<MetaNode>> {
+        self.resource
+            .read_with_metadata(meta_type, self.read_args(read_args))
+    }
+
+    /// Run a query with this revision of the resource as its context item
+    pub fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Value>> {
+        self.resource.query(query, Some(self.revision_arg()))
+    }
+}
+
+impl RevisionView<Xml> {
+    pub fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Element>> {
+        self.resource.read(self.read_args(read_args))
+    }
+
+    pub fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<Vec<XmlMetaNode>>> {
+        self.resource
+            .read_with_metadata(meta_type, self.read_args(read_args))
+    }
+
+    /// Run a query with this revision of the resource as its context item
+    pub fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Element>> {
+        self.resource.query(query, Some(self.revision_arg()))
+    }
+}
+
+/// The revisions of a resource from `first` to `last`, created by `Resource::between`.
+///
+/// Reads and queries cover every revision in the range, in the form SirixDB
+/// sends them, with each revision wrapped in its number and timestamp.
+#[derive(Debug, Clone)]
+pub struct RevisionRange<T> {
+    pub first: RevisionView<T>,
+    pub last: RevisionView<T>,
+}
+
+impl<T> RevisionRange<T> {
+    fn revisions(&self) -> TwoRevisions {
+        TwoRevisions::Number(self.first.revision, self.last.revision)
+    }
+
+    fn revision_arg(&self) -> RevisionArg {
+        RevisionArg::TwoRevisions(self.revisions())
+    }
+
+    fn read_args(&self, read_args: ReadArgs) -> ReadArgs {
+        ReadArgs {
+            revision: Some(self.revision_arg()),
+            ..read_args
+        }
+    }
+
+    /// The commits of the revisions in the range
+    pub fn history(&self) -> SirixResult<SirixResponse<History>> {
+        self.first.resource.history_with(HistoryQuery {
+            range: Some(self.revisions()),
+            limit: None,
+        })
+    }
+
+    /// The ETag of the node with the given id in the last revision of the range
+    pub fn etag(&self, node_id: u128) -> SirixResult<SirixResponse<String>> {
+        self.last.etag(node_id)
+    }
+
+    /// The changes from the first to the last revision
+    pub fn diff(&self) -> SirixResult<SirixResponse<DiffResult>> {
+        self.first.diff_to(&self.last)
+    }
+
+    /// The changes from the last revision of the range to `other`
+    pub fn diff_to(&self, other: &RevisionView<T>) -> SirixResult<SirixResponse<DiffResult>> {
+        self.last.diff_to(other)
+    }
+}
+
+impl RevisionRange<Json> {
+    pub fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Value>> {
+        self.first.resource.read(self.read_args(read_args))
+    }
+
+    pub fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<Value>> {
+        self.first
+            .resource
+            .read_with_metadata_raw(meta_type, self.read_args(read_args))
+    }
+
+    /// Run a query with each revision in the range as its context item
+    pub fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Value>> {
+        self.first.resource.query(query, Some(self.revision_arg()))
+    }
+}
+
+impl RevisionRange<Xml> {
+    pub fn read(&self, read_args: ReadArgs) -> SirixResult<SirixResponse<Element>> {
+        self.first.resource.read(self.read_args(read_args))
+    }
+
+    pub fn read_with_metadata(
+        &self,
+        meta_type: MetadataType,
+        read_args: ReadArgs,
+    ) -> SirixResult<SirixResponse<Element>> {
+        parse_body(
+            self.first
+                .resource
+                .read_with_metadata_string(meta_type, self.read_args(read_args))?,
+            parse_element,
+        )
+    }
+
+    /// Run a query with each revision in the range as its context item
+    pub fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Element>> {
+        self.first.resource.query(query, Some(self.revision_arg()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::mock::test_mocks;
+    use super::super::error::SirixError;
+    use super::*;
+    use crate::timestamp::parse_timestamp;
+    use mockito::{mock, Matcher};
+
+    fn commit(revision: u64, timestamp: &str) -> String {
+        format!(
+            r#"{{"history":[{{"revisionTimestamp":"{}","revision":{},"author":"admin","commitMessage":""}}]}}"#,
+            timestamp, revision
+        )
+    }
+
+    #[test]
+    fn test_revision_view() {
+        let _m = mock(
+            "GET",
+            "/database/revisions/history?startRevision=2&endRevision=2",
+        )
+        .with_status(200)
+        .with_body(commit(2, "2019-05-02T00:00:00"))
+        .create();
+        let _m2 = mock("GET", "/database/revisions?nodeId=3&revision=2")
+            .with_status(200)
+            .with_body(r#"["bar"]"#)
+            .create();
+        let _m3 = mock("GET", "/database/revisions")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), ".[0]".into()),
+                Matcher::UrlEncoded("revision".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"rest":["bar"]}"#)
+            .create();
+        let _m4 = mock("HEAD", "/database/revisions?nodeId=3&revision=2")
+            .with_status(200)
+            .with_header("etag", "abc123")
+            .create();
+        let view = test_mocks::json_resource("revisions")
+            .at(SingleRevision::Number(2))
+            .unwrap()
+            .clone();
+        assert_eq!(view.revision, 2);
+        assert_eq!(
+            view.timestamp,
+            parse_timestamp("2019-05-02T00:00:00").unwrap()
+        );
+        let read_args = ReadArgs {
+            node_id: Some(3),
+            // replaced by the revision of the view
+            revision: Some(RevisionArg::SingleRevision(SingleRevision::Number(5))),
+            ..ReadArgs::default()
+        };
+        assert_eq!(
+            view.read(read_args).unwrap().body,
+            serde_json::json!(["bar"])
+        );
+        assert_eq!(
+            view.query(".[0]").unwrap().body,
+            serde_json::json!({"rest": ["bar"]})
+        );
+        assert_eq!(view.etag(3).unwrap().body, "abc123");
+    }
+
+    #[test]
+    fn test_revision_view_at_timestamp() {
+        // `/history` cannot select commits by timestamp
+        let _m = mock("GET", "/database/revisions-at/history")
+            .with_status(200)
+            .with_body(
+                r#"{"history":[
+                    {"revisionTimestamp":"2019-05-04T00:00:00","revision":4,"author":"admin","commitMessage":""},
+                    {"revisionTimestamp":"2019-05-01T00:00:00","revision":1,"author":"admin","commitMessage":""},
+                    {"revisionTimestamp":"2019-05-03T00:00:00","revision":3,"author":"admin","commitMessage":""}
+                ]}"#,
+            )
+            .create();
+        let resource = test_mocks::json_resource("revisions-at");
+        let view = resource
+            .at(SingleRevision::Timestamp(
+                parse_timestamp("2019-05-03T12:00:00").unwrap(),
+            ))
+            .unwrap();
+        assert_eq!(view.revision, 3);
+        // there was no revision yet
+        let missing = resource.at(SingleRevision::Timestamp(
+            parse_timestamp("1970-01-02T00:00:00").unwrap(),
+        ));
+        assert!(matches!(missing, Err(SirixError::NotFound)));
+    }
+
+    #[test]
+    fn test_revision_view_checks_revision() {
+        let _m = mock(
+            "GET",
+            "/database/revisions-checked/history?startRevision=5&endRevision=5",
+        )
+        .with_status(200)
+        .with_body(commit(4, "2019-05-04T00:00:00"))
+        .create();
+        let missing = test_mocks::json_resource("revisions-checked").at(SingleRevision::Number(5));
+        assert!(matches!(missing, Err(SirixError::NotFound)));
+    }
+
+    #[test]
+    fn test_revision_range() {
+        let _m = mock(
+            "GET",
+            "/database/revisions/history?startRevision=4&endRevision=4",
+        )
+        .with_status(200)
+        .with_body(commit(4, "2019-05-04T00:00:00"))
+        .create();
+        let _m2 = mock(
+            "GET",
+            "/database/revisions/history?startRevision=6&endRevision=6",
+        )
+        .with_status(200)
+        .with_body(commit(6, "2019-05-06T00:00:00"))
+        .create();
+        let _m3 = mock(
+            "GET",
+            "/database/revisions/diff?first-revision=4&second-revision=6",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"database":"database","resource":"revisions","old-revision":4,"new-revision":6,"diffs":[]}"#,
+        )
+        .create();
+        let _m4 = mock("GET", "/database/revisions?start-revision=4&end-revision=6")
+            .with_status(200)
+            .with_body(r#"{"sirix":[]}"#)
+            .create();
+        let _m5 = mock(
+            "GET",
+            "/database/revisions?start-revision=4&end-revision=6&withMetadata=nodeKey",
+        )
+        .with_status(200)
+        .with_body(r#"{"sirix":[{"revisionNumber":4}]}"#)
+        .create();
+        let _m6 = mock("GET", "/database/revisions")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), "count(.)".into()),
+                Matcher::UrlEncoded("start-revision".into(), "4".into()),
+                Matcher::UrlEncoded("end-revision".into(), "6".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"rest":[1,1,1]}"#)
+            .create();
+        let _m7 = mock("HEAD", "/database/revisions?nodeId=1&revision=6")
+            .with_status(200)
+            .with_header("etag", "ghi789")
+            .create();
+        let range = test_mocks::json_resource("revisions")
+            .between(TwoRevisions::Number(4, 6))
+            .unwrap();
+        assert_eq!((range.first.revision, range.last.revision), (4, 6));
+        assert_eq!(range.diff().unwrap().body.new_revision, 6);
+        assert_eq!(
+            range.read(ReadArgs::default()).unwrap().body,
+            serde_json::json!({"sirix": []})
+        );
+        assert_eq!(
+            range
+                .read_with_metadata(MetadataType::Key, ReadArgs::default())
+                .unwrap()
+                .body,
+            serde_json::json!({"sirix": [{"revisionNumber": 4}]})
+        );
+        assert_eq!(
+            range.query("count(.)").unwrap().body,
+            serde_json::json!({"rest": [1, 1, 1]})
+        );
+        assert_eq!(range.etag(1).unwrap().body, "ghi789");
+    }
+
+    #[test]
+    fn test_xml_revision_view() {
+        let _history: Vec<_> = [1, 2]
+            .iter()
+            .map(|revision| {
+                mock(
+                    "GET",
+                    format!(
+                        "/database/revisions-xml/history?startRevision={}&endRevision={}",
+                        revision, revision
+                    )
+                    .as_str(),
+                )
+                .with_status(200)
+                .with_body(commit(*revision, "2019-05-01T00:00:00"))
+                .create()
+            })
+            .collect();
+        let _m = mock("GET", "/database/revisions-xml")
+            .match_header("accept", "application/xml")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("query".into(), "//book".into()),
+                Matcher::UrlEncoded("revision".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                r#"<rest:sequence xmlns:rest="https://sirix.io"><rest:item><book/></rest:item></rest:sequence>"#,
+            )
+            .create();
+        let _m2 = mock(
+            "GET",
+            "/database/revisions-xml/diff?first-revision=1&second-revision=2",
+        )
+        .with_status(200)
+        .with_body(
+            r#"{"database":"database","resource":"revisions-xml","old-revision":1,"new-revision":2,"diffs":[]}"#,
+        )
+        .create();
+        let resource = test_mocks::xml_resource("revisions-xml");
+        let first = resource.at(SingleRevision::Number(1)).unwrap();
+        let second = resource.at(SingleRevision::Number(2)).unwrap();
+        let sequence = first.query("//book").unwrap().body;
+        assert_eq!(sequence.children().count(), 1);
+        assert_eq!(first.diff_to(&second).unwrap().body.old_revision, 1);
+    }
+}
@@ -1,23 +1,23 @@
 use crate::types::{Json, Xml};
 
+use super::super::endpoints;
 use super::super::info;
-use super::super::types::{InfoResults, InfoResultsWithResourcesContainer};
-use super::client::SirixResponse;
+use super::super::transport::Transport;
+use super::super::types::{InfoResults, InfoResultsWithResourcesContainer, Query};
+use super::auth::{authorization, AuthHandle, AuthState};
+use super::builder::SirixBuilder;
+use super::client::{SirixResponse, UreqTransport};
 use super::database::Database;
 use super::error::SirixResult;
-use super::http::{
-    delete_all, global_info, global_info_string, global_info_with_resources,
-    global_info_with_resources_string,
-};
+use super::query::QueryPager;
 use serde::de::DeserializeOwned;
+use serde_json::Value;
 use std::{sync::Arc, sync::RwLock};
 
 #[derive(Debug, Clone)]
 pub struct Sirix {
-    /// the url for the SirixDB server
-    base_uri: String,
-    /// a ureq::Agent
-    agent: ureq::Agent,
+    /// sends requests to the SirixDB server
+    transport: UreqTransport,
     /// a reference to authentication data
     auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
 }
@@ -29,60 +29,58 @@ impl Sirix {
         auth_lock: Option<Arc<RwLock<Option<info::TokenData>>>>,
     ) -> Self {
         return Self {
-            base_uri,
-            agent,
+            transport: UreqTransport::new(agent, &base_uri),
             auth_lock,
         };
     }
 
+    /// Configure a client for the SirixDB server at `base_url`.
+    pub fn builder(base_url: &str) -> SirixBuilder {
+        SirixBuilder::new(base_url)
+    }
+
+    /// Create a client that owns its authentication thread, which stops once the last
+    /// clone of the client, and the last database or resource created from it, is dropped.
+    pub(crate) fn with_auth_handle(
+        base_uri: String,
+        agent: ureq::Agent,
+        auth_handle: Option<AuthHandle>,
+    ) -> Self {
+        let transport = UreqTransport::new(agent, &base_uri);
+        Self {
+            auth_lock: auth_handle.as_ref().map(AuthHandle::token),
+            transport: match auth_handle {
+                Some(auth_handle) => transport.with_auth_handle(Arc::new(auth_handle)),
+                None => transport,
+            },
+        }
+    }
+
+    /// The state of the authentication thread, if this client started it
+    pub fn auth_state(&self) -> Option<AuthState> {
+        self.transport.auth_handle().map(|handle| handle.state())
+    }
+
     pub fn json_database(&self, db_name: String) -> Database<Json> {
-        Database::<Json>::new(
-            db_name,
-            self.base_uri.clone(),
-            self.agent.clone(),
-            self.auth_lock.clone(),
-        )
+        Database::<Json>::with_transport(db_name, self.transport.clone(), self.auth_lock.clone())
     }
 
     pub fn xml_database(&self, db_name: String) -> Database<Xml> {
-        Database::<Xml>::new(
-            db_name,
-            self.base_uri.clone(),
-            self.agent.clone(),
-            self.auth_lock.clone(),
-        )
+        Database::<Xml>::with_transport(db_name, self.transport.clone(), self.auth_lock.clone())
     }
 
     pub fn info(&self) -> SirixResult<SirixResponse<InfoResults>> {
         self.info_raw()
     }
 
-    pub fn info_raw<U: DeserializeOwned>(&self) -> SirixResult<SirixResponse<U>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                global_info(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                )
-            }
-            None => global_info(self.agent.clone(), None, &self.base_uri),
-        }
+    pub fn info_raw<U: DeserializeOwned + Send + 'static>(&self) -> SirixResult<SirixResponse<U>> {
+        self.transport
+            .send_json(endpoints::global_info().authorization(authorization(&self.auth_lock)?))
     }
 
     pub fn info_string(&self) -> SirixResult<SirixResponse<String>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                global_info_string(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                )
-            }
-            None => global_info_string(self.agent.clone(), None, &self.base_uri),
-        }
+        self.transport
+            .send_string(endpoints::global_info().authorization(authorization(&self.auth_lock)?))
     }
 
     pub fn info_with_resources(
@@ -91,45 +89,79 @@ impl Sirix {
         self.info_with_resources_raw()
     }
 
-    pub fn info_with_resources_raw<U: DeserializeOwned>(&self) -> SirixResult<SirixResponse<U>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                global_info_with_resources(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                )
-            }
-            None => global_info_with_resources(self.agent.clone(), None, &self.base_uri),
-        }
+    pub fn info_with_resources_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+    ) -> SirixResult<SirixResponse<U>> {
+        self.transport.send_json(
+            endpoints::global_info_with_resources().authorization(authorization(&self.auth_lock)?),
+        )
     }
 
     pub fn info_with_resources_string(&self) -> SirixResult<SirixResponse<String>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                global_info_with_resources_string(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                )
-            }
-            None => global_info_with_resources_string(self.agent.clone(), None, &self.base_uri),
-        }
+        self.transport.send_string(
+            endpoints::global_info_with_resources().authorization(authorization(&self.auth_lock)?),
+        )
     }
 
     pub fn delete_all(&self) -> SirixResult<SirixResponse<()>> {
-        match self.auth_lock.clone() {
-            Some(lock) => {
-                let token_data = Arc::clone(&lock).read().unwrap().clone().unwrap();
-                delete_all(
-                    self.agent.clone(),
-                    Some(&token_data.access_token),
-                    &self.base_uri,
-                )
-            }
-            None => delete_all(self.agent.clone(), None, &self.base_uri),
-        }
+        self.transport
+            .send_no_content(endpoints::delete_all().authorization(authorization(&self.auth_lock)?))
+    }
+
+    /// Run a global JSONiq or XQuery query.
+    ///
+    /// Accepts a query string, or a `Query` to fetch only part of the result sequence.
+    pub fn query(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<Value>> {
+        self.query_raw(query)
+    }
+
+    /// Lazily iterate over the result sequence of a query, `page_size` items per request.
+    pub fn query_pager(&self, query: &str, page_size: u128) -> QueryPager {
+        QueryPager::new(self.clone(), query, page_size)
+    }
+
+    pub fn query_raw<U: DeserializeOwned + Send + 'static>(
+        &self,
+        query: impl Into<Query>,
+    ) -> SirixResult<SirixResponse<U>> {
+        self.transport.send_json(
+            endpoints::post_query(&query.into())?.authorization(authorization(&self.auth_lock)?),
+        )
+    }
+
+    pub fn query_string(&self, query: impl Into<Query>) -> SirixResult<SirixResponse<String>> {
+        self.transport.send_string(
+            endpoints::post_query(&query.into())?.authorization(authorization(&self.auth_lock)?),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn test_query() {
+        let url = &mockito::server_url();
+        let _m = mock("POST", "/")
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::Json(serde_json::json!({
+                "startResultSeqIndex": 0,
+                "endResultSeqIndex": 9,
+                "query": "jn:doc('database','resource')[]"
+            })))
+            .with_status(200)
+            .with_body(r#"{"rest":[1,2,3]}"#)
+            .create();
+        let sirix = Sirix::new(url.to_owned(), ureq::agent(), None);
+        let response = sirix
+            .query(Query {
+                start_result_seq_index: Some(0),
+                end_result_seq_index: Some(9),
+                ..Query::new("jn:doc('database','resource')[]")
+            })
+            .unwrap();
+        assert_eq!(response.body, serde_json::json!({"rest": [1, 2, 3]}));
     }
 }
@@ -0,0 +1,47 @@
+//! Streaming request bodies
+
+use std::io::{self, Read};
+
+/// Reports the number of bytes read from `reader` so far, as they are sent
+pub struct ProgressReader<R, F> {
+    reader: R,
+    progress: F,
+    sent: u64,
+}
+
+impl<R: Read, F: FnMut(u64)> ProgressReader<R, F> {
+    pub fn new(reader: R, progress: F) -> Self {
+        Self {
+            reader,
+            progress,
+            sent: 0,
+        }
+    }
+}
+
+impl<R: Read, F: FnMut(u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        if read > 0 {
+            self.sent += read as u64;
+            (self.progress)(self.sent);
+        }
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_reader() {
+        let mut reports = Vec::new();
+        let mut reader = ProgressReader::new(&b"[1,2,3]"[..], |sent| reports.push(sent));
+        let mut buf = [0; 4];
+        assert_eq!(reader.read(&mut buf).unwrap(), 4);
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert_eq!(reports, vec![4, 7]);
+    }
+}
@@ -0,0 +1,112 @@
+//! Revision timestamps.
+//!
+//! With the `chrono` feature, timestamps are `DateTime<Utc>`. Otherwise they are
+//! strings, passed to and from SirixDB unchanged.
+
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use thiserror::Error;
+
+/// A point in time, used to select and describe revisions
+#[cfg(feature = "chrono")]
+pub type Timestamp = DateTime<Utc>;
+
+/// A point in time, used to select and describe revisions
+#[cfg(not(feature = "chrono"))]
+pub type Timestamp = String;
+
+/// The format of timestamps in SirixDB query parameters, in UTC
+#[cfg(feature = "chrono")]
+const PARAM_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+/// A timestamp that could not be parsed
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid timestamp: {0:?}")]
+pub struct InvalidTimestamp(pub String);
+
+/// Format `timestamp` the way SirixDB expects it in query parameters,
+/// such as `2019-05-01T12:30:00.123`.
+#[cfg(feature = "chrono")]
+pub fn format_timestamp(timestamp: &Timestamp) -> String {
+    timestamp.naive_utc().format(PARAM_FORMAT).to_string()
+}
+
+/// Format `timestamp` the way SirixDB expects it in query parameters,
+/// such as `2019-05-01T12:30:00.123`.
+#[cfg(not(feature = "chrono"))]
+pub fn format_timestamp(timestamp: &Timestamp) -> String {
+    timestamp.clone()
+}
+
+/// Parse a timestamp as sent by SirixDB, either RFC 3339 or without an offset in UTC.
+#[cfg(feature = "chrono")]
+pub fn parse_timestamp(timestamp: &str) -> Result<Timestamp, InvalidTimestamp> {
+    match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(parsed) => Ok(parsed.with_timezone(&Utc)),
+        Err(_) => NaiveDateTime::parse_from_str(timestamp, PARAM_FORMAT)
+            .map(|parsed| parsed.and_utc())
+            .map_err(|_| InvalidTimestamp(timestamp.to_owned())),
+    }
+}
+
+/// Parse a timestamp as sent by SirixDB, either RFC 3339 or without an offset in UTC.
+#[cfg(not(feature = "chrono"))]
+pub fn parse_timestamp(timestamp: &str) -> Result<Timestamp, InvalidTimestamp> {
+    Ok(timestamp.to_owned())
+}
+
+pub(crate) fn deserialize_timestamp<'de, D>(deserializer: D) -> Result<Timestamp, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let timestamp = String::deserialize(deserializer)?;
+    parse_timestamp(&timestamp).map_err(serde::de::Error::custom)
+}
+
+#[cfg(all(test, feature = "chrono"))]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_round_trip() {
+        for timestamp in &[
+            "2019-05-01T12:30:00",
+            "2019-05-01T12:30:00.123",
+            "2019-05-01T12:30:00.123456",
+        ] {
+            assert_eq!(
+                &format_timestamp(&parse_timestamp(timestamp).unwrap()),
+                timestamp
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        let expected = Utc.with_ymd_and_hms(2019, 4, 30, 22, 0, 0).unwrap();
+        assert_eq!(
+            parse_timestamp("2019-05-01T00:00:00+02:00").unwrap(),
+            expected
+        );
+        assert_eq!(parse_timestamp("2019-04-30T22:00:00Z").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_format_in_utc() {
+        let timestamp = DateTime::parse_from_rfc3339("2019-05-01T00:00:00.5+02:00").unwrap();
+        assert_eq!(
+            format_timestamp(&timestamp.with_timezone(&Utc)),
+            "2019-04-30T22:00:00.500"
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(
+            parse_timestamp("yesterday"),
+            Err(InvalidTimestamp("yesterday".to_owned()))
+        );
+    }
+}
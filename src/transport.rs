@@ -0,0 +1,196 @@
+//! Requests to SirixDB, independent of the HTTP client that sends them.
+//!
+//! The endpoints are described once, in the `endpoints` module, and each client
+//! implements `Transport` to send them.
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::de::DeserializeOwned;
+use std::fmt;
+
+/// The characters to escape in a path segment, following RFC 3986
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// The HTTP methods used by the SirixDB API
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        use Method::*;
+        match self {
+            Get => "GET",
+            Head => "HEAD",
+            Post => "POST",
+            Put => "PUT",
+            Delete => "DELETE",
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A request to a SirixDB endpoint, relative to the URL of the server
+#[derive(Debug, Clone, PartialEq)]
+pub struct SirixRequest {
+    pub method: Method,
+    /// the segments of the path
+    pub path: Vec<String>,
+    /// the query parameters, not yet encoded
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+impl SirixRequest {
+    pub fn new(method: Method, path: &[&str]) -> Self {
+        Self {
+            method,
+            path: path.iter().map(|segment| segment.to_string()).collect(),
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn query(mut self, name: &str, value: impl ToString) -> Self {
+        self.query.push((name.to_owned(), value.to_string()));
+        self
+    }
+
+    pub fn queries(mut self, params: Vec<(String, String)>) -> Self {
+        self.query.extend(params);
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: impl ToString) -> Self {
+        self.headers.push((name.to_owned(), value.to_string()));
+        self
+    }
+
+    /// Set the `authorization` header, if there is a token
+    pub fn authorization(self, authorization: Option<String>) -> Self {
+        match authorization {
+            Some(authorization) => self.header("authorization", authorization),
+            None => self,
+        }
+    }
+
+    pub fn body(mut self, body: String) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// The encoded path and query string to append to the URL of the server
+    pub fn path_and_query(&self) -> String {
+        let segments: Vec<String> = self
+            .path
+            .iter()
+            .map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+            .collect();
+        let mut path_and_query = format!("/{}", segments.join("/"));
+        if !self.query.is_empty() {
+            path_and_query.push('?');
+            path_and_query.push_str(
+                &form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(&self.query)
+                    .finish(),
+            );
+        }
+        path_and_query
+    }
+}
+
+/// Sends `SirixRequest`s with an HTTP client.
+///
+/// `Output<T>` is the result of the request for a blocking client, and a future
+/// resolving to that result for an asynchronous one. `Stream<'a>` is a request body
+/// that is sent as it is read, such as a reader for a blocking client.
+pub trait Transport {
+    type Output<T>;
+    type Stream<'a>;
+
+    /// Send the request and deserialize the JSON response body
+    fn send_json<T: DeserializeOwned + Send + 'static>(
+        &self,
+        request: SirixRequest,
+    ) -> Self::Output<T>;
+
+    /// Send the request and return the response body as text
+    fn send_string(&self, request: SirixRequest) -> Self::Output<String>;
+
+    /// Send the request and discard the response body
+    fn send_no_content(&self, request: SirixRequest) -> Self::Output<()>;
+
+    /// Send the request with its body streamed from `body`, instead of `request.body`,
+    /// and discard the response body
+    fn send_stream<'a>(&self, request: SirixRequest, body: Self::Stream<'a>) -> Self::Output<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_and_query() {
+        let request = SirixRequest::new(Method::Get, &["database", "resource"])
+            .query("query", "for $i in .[] return $i")
+            .query("revision", 3);
+        assert_eq!(
+            request.path_and_query(),
+            "/database/resource?query=for+%24i+in+.%5B%5D+return+%24i&revision=3"
+        );
+    }
+
+    #[test]
+    fn test_encode_path_segments() {
+        let request = SirixRequest::new(Method::Get, &["my database", "50%/übersicht?"])
+            .query("revision-timestamp", "2019-05-01T00:00:00+02:00");
+        assert_eq!(
+            request.path_and_query(),
+            "/my%20database/50%25%2F%C3%BCbersicht%3F?revision-timestamp=2019-05-01T00%3A00%3A00%2B02%3A00"
+        );
+    }
+
+    #[test]
+    fn test_root_path() {
+        let request = SirixRequest::new(Method::Get, &[]);
+        assert_eq!(request.path_and_query(), "/");
+    }
+
+    #[test]
+    fn test_authorization() {
+        let request = SirixRequest::new(Method::Get, &[]).authorization(None);
+        assert!(request.headers.is_empty());
+        let request = request.authorization(Some("bearer token".to_owned()));
+        assert_eq!(
+            request.headers,
+            vec![("authorization".to_owned(), "bearer token".to_owned())]
+        );
+    }
+}
@@ -1,73 +1,164 @@
 //! The various types used in SirixDB transactions
 
-use super::info::{NodeTypeContainer, NodeTypePrimitive};
-use serde::{Deserialize, Deserializer, Serialize};
+use super::info::{NodeType, NodeTypeContainer, NodeTypePrimitive};
+use super::timestamp::{deserialize_timestamp, Timestamp};
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::ops::Deref;
+use thiserror::Error;
 
 /// A single commit
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Commit {
-    revision_timestamp: String,
-    revision: usize,
-    author: String,
-    commit_message: String,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub revision_timestamp: Timestamp,
+    pub revision: usize,
+    pub author: String,
+    pub commit_message: String,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct History(Vec<Commit>);
+/// The commits of a resource, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct History {
+    pub history: Vec<Commit>,
+}
+
+impl Deref for History {
+    type Target = [Commit];
+
+    fn deref(&self) -> &[Commit] {
+        &self.history
+    }
+}
+
+impl IntoIterator for History {
+    type Item = Commit;
+    type IntoIter = std::vec::IntoIter<Commit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.history.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a History {
+    type Item = &'a Commit;
+    type IntoIter = std::slice::Iter<'a, Commit>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.history.iter()
+    }
+}
 
 type Resources = Vec<String>;
 
 /// database info struct
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct DbInfo {
-    resources: Resources, // should this be full-on resources?
+    pub resources: Resources, // should this be full-on resources?
 }
 
 /// A diff from a delete operation
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DeleteDiff {
-    node_key: usize,
-    dewey_id: String,
-    depth: u64,
+    pub node_key: u128,
+    #[serde(rename = "deweyID", default)]
+    pub dewey_id: Option<String>,
+    #[serde(default)]
+    pub depth: Option<u64>,
+}
+
+/// A single change between two revisions of a resource
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Diff {
+    Insert(InsertDiff),
+    Replace(ReplaceDiff),
+    Update(UpdateDiff),
+    Delete(DeleteDiff),
+}
+
+/// The result of a diff request between two revisions of a resource
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DiffResult {
+    pub database: String,
+    pub resource: String,
+    #[serde(rename = "old-revision")]
+    pub old_revision: u64,
+    #[serde(rename = "new-revision")]
+    pub new_revision: u64,
+    pub diffs: Vec<Diff>,
 }
 
 /// A result from the global info request
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct InfoResult {
-    name: String,
-    info_type: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub info_type: String,
 }
 
 /// A result from the global info request, resources included
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct InfoResultWithResources {
-    name: String,
-    info_type: String,
-    resources: Resources,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub info_type: String,
+    pub resources: Resources,
 }
 
 /// A full list for the global info request, without resources
-#[derive(Debug, Default, Deserialize)]
-pub struct InfoResults(Vec<InfoResult>);
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct InfoResults(pub Vec<InfoResult>);
 
 /// A full list for the global info request, with resources
 //#[derive(Debug, Default, Deserialize)]
 pub type InfoResultsWithResources = Vec<InfoResultWithResources>;
 
 /// A full list for the global info request, with resources
-#[derive(Debug, Default, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
 pub struct InfoResultsWithResourcesContainer {
     pub databases: InfoResultsWithResources,
 }
 
-#[derive(Debug, Default, Serialize)]
+/// A JSONiq or XQuery query, optionally limited to a slice of the result sequence
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct Query {
-    #[serde(rename = "startResultSeqIndex")]
-    start_result_seq_index: Option<u128>,
-    #[serde(rename = "endResultSeqIndex")]
-    end_result_seq_index: Option<u128>,
-    query: String,
+    #[serde(
+        rename = "startResultSeqIndex",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start_result_seq_index: Option<u128>,
+    #[serde(rename = "endResultSeqIndex", skip_serializing_if = "Option::is_none")]
+    pub end_result_seq_index: Option<u128>,
+    pub query: String,
+}
+
+impl Query {
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for Query {
+    fn from(query: &str) -> Self {
+        Query::new(query)
+    }
+}
+
+impl From<String> for Query {
+    fn from(query: String) -> Self {
+        Self {
+            query,
+            ..Default::default()
+        }
+    }
 }
 
 pub struct NodeIdAndEtag {
@@ -75,21 +166,25 @@ pub struct NodeIdAndEtag {
     pub etag: String,
 }
 
+#[derive(Debug, Clone)]
 pub enum RevisionArg {
     SingleRevision(SingleRevision),
     TwoRevisions(TwoRevisions),
 }
 
+#[derive(Debug, Clone)]
 pub enum SingleRevision {
-    Timestamp(String),
+    Timestamp(Timestamp),
     Number(u64),
 }
 
+#[derive(Debug, Clone)]
 pub enum TwoRevisions {
-    Timestamp(String, String),
+    Timestamp(Timestamp, Timestamp),
     Number(u64, u64),
 }
 
+#[derive(Default)]
 pub struct ReadArgs {
     pub node_id: Option<u128>,
     pub revision: Option<RevisionArg>,
@@ -98,6 +193,33 @@ pub struct ReadArgs {
     pub top_level_skip_last_node: Option<u64>,
 }
 
+/// Recorded by SirixDB in the `Commit` of a write
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// shown as `Commit::commit_message` in the history
+    pub commit_message: Option<String>,
+    /// recorded as `Commit::revision_timestamp`, instead of the time of the write
+    pub commit_timestamp: Option<Timestamp>,
+}
+
+impl WriteOptions {
+    pub fn message(commit_message: &str) -> Self {
+        Self {
+            commit_message: Some(commit_message.to_owned()),
+            ..Default::default()
+        }
+    }
+}
+
+/// Selects the commits returned by `history_with`
+#[derive(Debug, Clone, Default)]
+pub struct HistoryQuery {
+    /// only the commits in this range of revisions, or of commit timestamps
+    pub range: Option<TwoRevisions>,
+    /// at most this many commits, the most recent ones
+    pub limit: Option<u64>,
+}
+
 pub struct DiffArgs {
     pub first_revision: SingleRevision,
     pub second_revision: SingleRevision,
@@ -106,37 +228,107 @@ pub struct DiffArgs {
 }
 
 /// A diff from an insert operation
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InsertDiff {
-    node_key: u128,
-    insert_position_node_key: usize,
-    insert_position: String,
-    dewey_id: String,
-    depth: u64,
-    insert_type: String,
-    data: String,
+    pub node_key: u128,
+    pub insert_position_node_key: u128,
+    pub insert_position: String,
+    #[serde(rename = "deweyID", default)]
+    pub dewey_id: Option<String>,
+    #[serde(default)]
+    pub depth: Option<u64>,
+    #[serde(rename = "type")]
+    pub insert_type: String,
+    pub data: String,
 }
 
-#[derive(Debug, Deserialize)]
+/// The metadata of an object key or a value without children.
+///
+/// Which fields SirixDB sends depends on the `MetadataType` of the request;
+/// only `node_key` is always present.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MetadataPrimitive {
-    node_key: usize,
-    hash: isize,
-    node_type: NodeTypePrimitive,
+    pub node_key: usize,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_hash",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hash: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub node_type: Option<NodeTypePrimitive>,
+    /// only sent for object keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descendant_count: Option<usize>,
 }
 
-#[derive(Debug, Deserialize)]
+/// The metadata of an object or an array.
+///
+/// Which fields SirixDB sends depends on the `MetadataType` of the request;
+/// only `node_key` is always present.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MetadataContainer {
-    node_key: usize,
-    hash: isize,
-    node_type: NodeTypeContainer,
-    descendant_count: usize,
-    child_count: usize,
+    pub node_key: usize,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_hash",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hash: Option<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub node_type: Option<NodeTypeContainer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub descendant_count: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub child_count: Option<usize>,
+}
+
+/// SirixDB sends hashes as hex strings, older versions as numbers
+fn deserialize_hash<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct HashVisitor;
+
+    impl<'de> Visitor<'de> for HashVisitor {
+        type Value = Option<String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a hash as string or integer")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+            Ok(Some(value.to_owned()))
+        }
+
+        fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+            Ok(Some(value.to_string()))
+        }
+
+        fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+            Ok(Some(value.to_string()))
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+    }
+
+    deserializer.deserialize_any(HashVisitor)
 }
 
-#[derive(Debug, Deserialize)]
+/// A node of a resource read with metadata, see `MetadataType`.
+///
+/// Without `MetadataType::All` SirixDB does not send the node types, so
+/// containers are told apart by their content: the members of an object are
+/// object keys, and an empty object is sent as `{}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(untagged)]
 pub enum MetaNode {
-    OjbectKey(MetaNodeObjectKey),
+    ObjectKey(MetaNodeObjectKey),
     Array(MetaNodeArray),
     Object(MetaNodeObject),
     String(MetaNodeString),
@@ -145,86 +337,610 @@ pub enum MetaNode {
     Null(MetaNodeNull),
 }
 
-#[derive(Debug, Deserialize)]
+/// A step from a container to one of its children
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// the member of an object with the given key
+    Key(String),
+    /// the element of an array at the given index
+    Index(usize),
+}
+
+/// A path that is neither a JSON Pointer nor of the form `a.b[3].c`
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid path: {0:?}")]
+pub struct InvalidPath(pub String);
+
+/// A page of query results that is not of the form `{"rest": [...]}`
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("unexpected page of query results: {0}")]
+pub struct UnexpectedQueryResult(pub serde_json::Value);
+
+/// Parse a path to a node in a JSON resource.
+///
+/// Paths starting with `/` are JSON Pointers (RFC 6901), whose segments are all
+/// returned as `PathSegment::Key`, since only the document tells whether `0`
+/// is a key or an index. Other paths are of the form `a.b[3].c`. The empty
+/// path is the root node.
+pub fn parse_path(path: &str) -> Result<Vec<PathSegment>, InvalidPath> {
+    let invalid = || InvalidPath(path.to_owned());
+    if let Some(pointer) = path.strip_prefix('/') {
+        return pointer
+            .split('/')
+            .map(|token| unescape_pointer_token(token).map(PathSegment::Key))
+            .collect::<Option<_>>()
+            .ok_or_else(invalid);
+    }
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(index) = rest.strip_prefix('[') {
+            let end = index.find(']').ok_or_else(invalid)?;
+            let digits = &index[..end];
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+            segments.push(PathSegment::Index(digits.parse().map_err(|_| invalid())?));
+            rest = &index[end + 1..];
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            segments.push(PathSegment::Key(rest[..end].to_owned()));
+            rest = &rest[end..];
+        }
+        if let Some(key) = rest.strip_prefix('.') {
+            // a key has to follow the dot
+            if key.is_empty() || key.starts_with('.') || key.starts_with('[') {
+                return Err(invalid());
+            }
+            rest = key;
+        } else if !rest.is_empty() && !rest.starts_with('[') {
+            return Err(invalid());
+        }
+    }
+    Ok(segments)
+}
+
+/// Replace `~1` with `/` and `~0` with `~`, or `None` for any other escape
+fn unescape_pointer_token(token: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next() {
+                Some('0') => unescaped.push('~'),
+                Some('1') => unescaped.push('/'),
+                _ => return None,
+            },
+            c => unescaped.push(c),
+        }
+    }
+    Some(unescaped)
+}
+
+impl MetaNode {
+    /// The node key, to use as `node_id` in `update` and `delete`
+    pub fn node_key(&self) -> usize {
+        match self {
+            MetaNode::ObjectKey(node) => node.metadata.node_key,
+            MetaNode::Array(node) => node.metadata.node_key,
+            MetaNode::Object(node) => node.metadata.node_key,
+            MetaNode::String(node) => node.metadata.node_key,
+            MetaNode::Number(node) => node.metadata.node_key,
+            MetaNode::Bool(node) => node.metadata.node_key,
+            MetaNode::Null(node) => node.metadata.node_key,
+        }
+    }
+
+    /// The hash of the node, only sent with `MetadataType::All`
+    pub fn hash(&self) -> Option<&str> {
+        match self {
+            MetaNode::ObjectKey(node) => node.metadata.hash.as_deref(),
+            MetaNode::Array(node) => node.metadata.hash.as_deref(),
+            MetaNode::Object(node) => node.metadata.hash.as_deref(),
+            MetaNode::String(node) => node.metadata.hash.as_deref(),
+            MetaNode::Number(node) => node.metadata.hash.as_deref(),
+            MetaNode::Bool(node) => node.metadata.hash.as_deref(),
+            MetaNode::Null(node) => node.metadata.hash.as_deref(),
+        }
+    }
+
+    /// The children of the node: the object keys of an object, the elements of
+    /// an array, or the value of an object key.
+    pub fn children(&self) -> &[MetaNode] {
+        match self {
+            MetaNode::ObjectKey(node) => std::slice::from_ref(&node.value),
+            MetaNode::Array(node) => &node.value,
+            MetaNode::Object(node) => &node.value,
+            _ => &[],
+        }
+    }
+
+    /// Like `children`, taking ownership of them
+    pub fn into_children(self) -> Vec<MetaNode> {
+        match self {
+            MetaNode::ObjectKey(node) => vec![*node.value],
+            MetaNode::Array(node) => node.value,
+            MetaNode::Object(node) => node.value,
+            _ => Vec::new(),
+        }
+    }
+
+    /// The value of the member `key`, if this is an object containing it
+    pub fn get(&self, key: &str) -> Option<&MetaNode> {
+        match self {
+            MetaNode::Object(node) => node.value.iter().find_map(|member| match member {
+                MetaNode::ObjectKey(member) if member.key == key => Some(&*member.value),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The element at `index`, if this is an array long enough
+    pub fn index(&self, index: usize) -> Option<&MetaNode> {
+        match self {
+            MetaNode::Array(node) => node.value.get(index),
+            _ => None,
+        }
+    }
+
+    /// The node at `path` from this node, see `parse_path`.
+    ///
+    /// A `PathSegment::Key` selects the element of an array if it is an index,
+    /// as in JSON Pointers.
+    pub fn at_path(&self, path: &[PathSegment]) -> Option<&MetaNode> {
+        path.iter()
+            .try_fold(self, |node, segment| node.child_at(segment))
+    }
+
+    /// Like `at_path`, but returns the object key instead of its value if the
+    /// path ends at the member of an object.
+    ///
+    /// This is the node to delete to remove the member.
+    pub fn member_at_path(&self, path: &[PathSegment]) -> Option<&MetaNode> {
+        let (last, parent) = match path.split_last() {
+            Some(split) => split,
+            None => return Some(self),
+        };
+        match (self.at_path(parent)?, last) {
+            (MetaNode::Object(object), PathSegment::Key(key)) => {
+                object.value.iter().find(|member| match member {
+                    MetaNode::ObjectKey(member) => &member.key == key,
+                    _ => false,
+                })
+            }
+            (parent, last) => parent.child_at(last),
+        }
+    }
+
+    fn child_at(&self, segment: &PathSegment) -> Option<&MetaNode> {
+        match (self, segment) {
+            (MetaNode::Object(_), PathSegment::Key(key)) => self.get(key),
+            (MetaNode::Array(_), PathSegment::Key(key)) => self.index(key.parse().ok()?),
+            (MetaNode::Array(_), PathSegment::Index(index)) => self.index(*index),
+            _ => None,
+        }
+    }
+
+    /// This node or a descendant with the given node key, including object keys
+    pub fn find_by_node_key(&self, node_key: usize) -> Option<&MetaNode> {
+        if self.node_key() == node_key {
+            return Some(self);
+        }
+        self.children()
+            .iter()
+            .find_map(|child| child.find_by_node_key(node_key))
+    }
+
+    /// Iterate depth-first over this node and its descendants, with their path
+    /// from this node.
+    ///
+    /// Object keys are not yielded themselves, their values are yielded with
+    /// the key as the last segment of the path.
+    pub fn iter(&self) -> MetaNodeIter<'_> {
+        MetaNodeIter {
+            stack: vec![(Vec::new(), self)],
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a MetaNode {
+    type Item = (Vec<PathSegment>, &'a MetaNode);
+    type IntoIter = MetaNodeIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Depth-first iterator over a `MetaNode` and its descendants, see `MetaNode::iter`
+pub struct MetaNodeIter<'a> {
+    stack: Vec<(Vec<PathSegment>, &'a MetaNode)>,
+}
+
+impl<'a> Iterator for MetaNodeIter<'a> {
+    type Item = (Vec<PathSegment>, &'a MetaNode);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (path, node) = self.stack.pop()?;
+            match node {
+                MetaNode::ObjectKey(object_key) => {
+                    // yield the value instead, with the key as last segment
+                    let mut value_path = path;
+                    value_path.push(PathSegment::Key(object_key.key.clone()));
+                    self.stack.push((value_path, &object_key.value));
+                    continue;
+                }
+                MetaNode::Object(object) => {
+                    for member in object.value.iter().rev() {
+                        self.stack.push((path.clone(), member));
+                    }
+                }
+                MetaNode::Array(array) => {
+                    for (index, element) in array.value.iter().enumerate().rev() {
+                        let mut element_path = path.clone();
+                        element_path.push(PathSegment::Index(index));
+                        self.stack.push((element_path, element));
+                    }
+                }
+                _ => {}
+            }
+            return Some((path, node));
+        }
+    }
+}
+
+/// The document without metadata
+impl From<MetaNode> for serde_json::Value {
+    fn from(node: MetaNode) -> Self {
+        match node {
+            MetaNode::ObjectKey(node) => {
+                let mut object = serde_json::Map::new();
+                object.insert(node.key, (*node.value).into());
+                serde_json::Value::Object(object)
+            }
+            MetaNode::Object(node) => serde_json::Value::Object(
+                node.value
+                    .into_iter()
+                    .filter_map(|member| match member {
+                        MetaNode::ObjectKey(member) => Some((member.key, (*member.value).into())),
+                        _ => None,
+                    })
+                    .collect(),
+            ),
+            MetaNode::Array(node) => {
+                serde_json::Value::Array(node.value.into_iter().map(Into::into).collect())
+            }
+            MetaNode::String(node) => node.value.into(),
+            MetaNode::Number(node) => node.value.into(),
+            MetaNode::Bool(node) => node.value.into(),
+            MetaNode::Null(_) => serde_json::Value::Null,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MetaNode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MetaNodeVisitor;
+
+        impl<'de> Visitor<'de> for MetaNodeVisitor {
+            type Value = MetaNode;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a node with metadata")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                node_from_map(map)?.ok_or_else(|| de::Error::missing_field("metadata"))
+            }
+        }
+
+        deserializer.deserialize_map(MetaNodeVisitor)
+    }
+}
+
+/// The metadata of any node, before we know which kind of node it belongs to
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawMetadata {
+    node_key: usize,
+    #[serde(default, deserialize_with = "deserialize_hash")]
+    hash: Option<String>,
+    #[serde(rename = "type", default)]
+    node_type: Option<NodeType>,
+    #[serde(default)]
+    descendant_count: Option<usize>,
+    #[serde(default)]
+    child_count: Option<usize>,
+}
+
+impl RawMetadata {
+    fn primitive(self) -> Result<MetadataPrimitive, String> {
+        let node_type = match self.node_type {
+            Some(NodeType::Primitive(node_type)) => Some(node_type),
+            Some(NodeType::Container(node_type)) => {
+                return Err(format!("unexpected value for {} node", node_type))
+            }
+            None => None,
+        };
+        Ok(MetadataPrimitive {
+            node_key: self.node_key,
+            hash: self.hash,
+            node_type,
+            descendant_count: self.descendant_count,
+        })
+    }
+
+    fn container(self) -> Result<MetadataContainer, String> {
+        let node_type = match self.node_type {
+            Some(NodeType::Container(node_type)) => Some(node_type),
+            Some(NodeType::Primitive(node_type)) => {
+                return Err(format!("unexpected children for {} node", node_type))
+            }
+            None => None,
+        };
+        Ok(MetadataContainer {
+            node_key: self.node_key,
+            hash: self.hash,
+            node_type,
+            descendant_count: self.descendant_count,
+            child_count: self.child_count,
+        })
+    }
+}
+
+/// The `value` of a node, before we know which kind of node it belongs to
+enum RawValue {
+    Nodes(Vec<MetaNode>),
+    Node(Box<MetaNode>),
+    EmptyObject,
+    String(String),
+    Number(serde_json::Number),
+    Bool(bool),
+    Null,
+}
+
+impl<'de> Deserialize<'de> for RawValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RawValueVisitor;
+
+        impl<'de> Visitor<'de> for RawValueVisitor {
+            type Value = RawValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the value of a node")
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<Self::Value, E> {
+                Ok(RawValue::Bool(value))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+                Ok(RawValue::Number(value.into()))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+                Ok(RawValue::Number(value.into()))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<Self::Value, E> {
+                serde_json::Number::from_f64(value)
+                    .map(RawValue::Number)
+                    .ok_or_else(|| E::custom(format!("invalid number {}", value)))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                Ok(RawValue::String(value.to_owned()))
+            }
+
+            fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+                Ok(RawValue::String(value))
+            }
+
+            fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+                Ok(RawValue::Null)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut nodes = Vec::new();
+                while let Some(node) = seq.next_element()? {
+                    nodes.push(node);
+                }
+                Ok(RawValue::Nodes(nodes))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                Ok(match node_from_map(map)? {
+                    Some(node) => RawValue::Node(Box::new(node)),
+                    None => RawValue::EmptyObject,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(RawValueVisitor)
+    }
+}
+
+/// Read a node from `map`, or `None` if the map is empty
+fn node_from_map<'de, A: MapAccess<'de>>(mut map: A) -> Result<Option<MetaNode>, A::Error> {
+    let mut empty = true;
+    let mut metadata: Option<RawMetadata> = None;
+    let mut key: Option<String> = None;
+    let mut value: Option<RawValue> = None;
+    while let Some(field) = map.next_key::<String>()? {
+        empty = false;
+        match field.as_str() {
+            "metadata" => metadata = Some(map.next_value()?),
+            "key" => key = Some(map.next_value()?),
+            "value" => value = Some(map.next_value()?),
+            _ => {
+                map.next_value::<de::IgnoredAny>()?;
+            }
+        }
+    }
+    if empty {
+        return Ok(None);
+    }
+    let metadata = metadata.ok_or_else(|| de::Error::missing_field("metadata"))?;
+    node_from_parts(metadata, key, value.unwrap_or(RawValue::Null))
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+fn node_from_parts(
+    metadata: RawMetadata,
+    key: Option<String>,
+    value: RawValue,
+) -> Result<MetaNode, String> {
+    Ok(match (key, value) {
+        (Some(key), RawValue::Node(value)) => MetaNode::ObjectKey(MetaNodeObjectKey {
+            metadata: metadata.primitive()?,
+            key,
+            value,
+        }),
+        (Some(key), _) => return Err(format!("object key {:?} without a node as value", key)),
+        (None, RawValue::Node(_)) => {
+            return Err("only object keys can have a node as value".to_owned())
+        }
+        (None, RawValue::EmptyObject) => MetaNode::Object(MetaNodeObject {
+            metadata: metadata.container()?,
+            value: Vec::new(),
+        }),
+        (None, RawValue::Nodes(value)) => {
+            let is_object = match metadata.node_type {
+                Some(NodeType::Container(NodeTypeContainer::Object)) => true,
+                Some(_) => false,
+                None => matches!(value.first(), Some(MetaNode::ObjectKey(_))),
+            };
+            let metadata = metadata.container()?;
+            if is_object {
+                MetaNode::Object(MetaNodeObject { metadata, value })
+            } else {
+                MetaNode::Array(MetaNodeArray { metadata, value })
+            }
+        }
+        (None, RawValue::String(value)) => MetaNode::String(MetaNodeString {
+            metadata: metadata.primitive()?,
+            value,
+        }),
+        (None, RawValue::Number(value)) => MetaNode::Number(MetaNodeNumber {
+            metadata: metadata.primitive()?,
+            value,
+        }),
+        (None, RawValue::Bool(value)) => MetaNode::Bool(MetaNodeBool {
+            metadata: metadata.primitive()?,
+            value,
+        }),
+        (None, RawValue::Null) => MetaNode::Null(MetaNodeNull {
+            metadata: metadata.primitive()?,
+            value: (),
+        }),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MetaNodeObjectKey {
-    pub metadata: MetadataPrimitive,
     pub key: String,
+    pub metadata: MetadataPrimitive,
     pub value: Box<MetaNode>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MetaNodeObject {
     pub metadata: MetadataContainer,
-    #[serde(deserialize_with = "from_list_or_empty_object")]
+    #[serde(serialize_with = "serialize_members")]
     pub value: Vec<MetaNode>,
 }
 
-fn from_list_or_empty_object<'de, D>(deserializer: D) -> Result<Vec<MetaNode>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let s: &str = Deserialize::deserialize(deserializer)?;
-    if s.chars().nth(0).unwrap().eq(&'{') {
-        Ok(Vec::new())
+/// SirixDB sends an empty object as `{}`, and the members of an object as a list
+fn serialize_members<S: Serializer>(value: &[MetaNode], serializer: S) -> Result<S::Ok, S::Error> {
+    if value.is_empty() {
+        serializer.serialize_map(Some(0))?.end()
     } else {
-        let vec: Result<Vec<MetaNode>, serde_json::Error> = serde_json::from_str(s);
-        match vec {
-            Ok(val) => Ok(val),
-            Err(err) => Err(serde::de::Error::custom(err)),
-        }
+        value.serialize(serializer)
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MetaNodeArray {
     pub metadata: MetadataContainer,
     pub value: Vec<MetaNode>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MetaNodeString {
     pub metadata: MetadataPrimitive,
     pub value: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MetaNodeNumber {
     pub metadata: MetadataPrimitive,
     pub value: serde_json::Number,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MetaNodeBool {
     pub metadata: MetadataPrimitive,
     pub value: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct MetaNodeNull {
     pub metadata: MetadataPrimitive,
+    /// always `null`
+    pub value: (),
 }
 
 /// The result returned from a query
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct QueryResult {
-    revision_number: usize,
-    revision_timestamp: String,
-    revision: Revision,
+    pub revision_number: usize,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub revision_timestamp: Timestamp,
+    pub revision: Revision,
 }
 
 /// A diff from a replace operation
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ReplaceDiff {
-    node_key: usize,
-    replace_type: String,
-    data: String,
+    pub old_node_key: u128,
+    pub new_node_key: u128,
+    #[serde(rename = "deweyID", default)]
+    pub dewey_id: Option<String>,
+    #[serde(default)]
+    pub depth: Option<u64>,
+    #[serde(rename = "type")]
+    pub replace_type: String,
+    pub data: String,
 }
 
 /// A timestamped revision ID
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Revision {
-    timestamp: String,
-    revision: usize,
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub timestamp: Timestamp,
+    pub revision: usize,
+}
+
+/// A diff from an update of a node's value
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDiff {
+    pub node_key: u128,
+    #[serde(rename = "deweyID", default)]
+    pub dewey_id: Option<String>,
+    #[serde(default)]
+    pub depth: Option<u64>,
+    #[serde(rename = "type")]
+    pub update_type: String,
+    pub value: serde_json::Value,
 }
 
 /// All possible options for a resource update
@@ -292,3 +1008,365 @@ impl fmt::Display for MetadataType {
         write!(f, "{}", db_type)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_diff_result() {
+        let json = r#"{
+            "database": "database",
+            "resource": "resource",
+            "old-revision": 1,
+            "new-revision": 2,
+            "diffs": [
+                {
+                    "insert": {
+                        "nodeKey": 4,
+                        "insertPositionNodeKey": 3,
+                        "insertPosition": "asFirstChild",
+                        "deweyID": "1.17.17",
+                        "depth": 2,
+                        "type": "jsonFragment",
+                        "data": "{\"tadaaa\":\"todooo\"}"
+                    }
+                },
+                {
+                    "replace": {
+                        "oldNodeKey": 5,
+                        "newNodeKey": 6,
+                        "type": "jsonFragment",
+                        "data": "[1,2]"
+                    }
+                },
+                {
+                    "update": {
+                        "nodeKey": 7,
+                        "type": "string",
+                        "value": "foo"
+                    }
+                },
+                {
+                    "delete": {
+                        "nodeKey": 8,
+                        "deweyID": "1.3",
+                        "depth": 1
+                    }
+                }
+            ]
+        }"#;
+        let result: DiffResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.database, "database");
+        assert_eq!(result.old_revision, 1);
+        assert_eq!(result.new_revision, 2);
+        assert_eq!(result.diffs.len(), 4);
+        match &result.diffs[0] {
+            Diff::Insert(diff) => {
+                assert_eq!(diff.node_key, 4);
+                assert_eq!(diff.insert_position_node_key, 3);
+                assert_eq!(diff.dewey_id.as_deref(), Some("1.17.17"));
+                assert_eq!(diff.data, r#"{"tadaaa":"todooo"}"#);
+            }
+            diff => panic!("expected insert diff, got {:?}", diff),
+        }
+        match &result.diffs[1] {
+            Diff::Replace(diff) => {
+                assert_eq!(diff.old_node_key, 5);
+                assert_eq!(diff.new_node_key, 6);
+                assert_eq!(diff.depth, None);
+            }
+            diff => panic!("expected replace diff, got {:?}", diff),
+        }
+        match &result.diffs[2] {
+            Diff::Update(diff) => assert_eq!(diff.value, serde_json::json!("foo")),
+            diff => panic!("expected update diff, got {:?}", diff),
+        }
+        match &result.diffs[3] {
+            Diff::Delete(diff) => assert_eq!(diff.node_key, 8),
+            diff => panic!("expected delete diff, got {:?}", diff),
+        }
+    }
+
+    #[test]
+    fn test_history() {
+        let json = r#"{"history":[
+            {"revisionTimestamp":"2019-05-01T12:30:00.123","revision":1,"author":"admin","commitMessage":"init"},
+            {"revisionTimestamp":"2019-05-02T08:00:00","revision":2,"author":"admin","commitMessage":""}
+        ]}"#;
+        let history: History = serde_json::from_str(json).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].commit_message, "init");
+        let revisions: Vec<usize> = history.iter().map(|commit| commit.revision).collect();
+        assert_eq!(revisions, vec![1, 2]);
+
+        let round_trip: History =
+            serde_json::from_str(&serde_json::to_string(&history).unwrap()).unwrap();
+        assert_eq!(round_trip, history);
+        assert_eq!(round_trip.into_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_info_results_round_trip() {
+        let json = r#"{"databases":[{"name":"database","type":"json","resources":["resource"]}]}"#;
+        let info: InfoResultsWithResourcesContainer = serde_json::from_str(json).unwrap();
+        assert_eq!(info.databases[0].info_type, "json");
+        assert_eq!(info.databases[0].resources, vec!["resource"]);
+        assert_eq!(
+            serde_json::to_value(&info).unwrap(),
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+    }
+
+    const METADATA_ALL: &str = include_str!("../tests/resources/metadata/all.json");
+    const METADATA_NODE_KEY: &str = include_str!("../tests/resources/metadata/node_key.json");
+    const METADATA_NODE_KEY_AND_CHILD_COUNT: &str =
+        include_str!("../tests/resources/metadata/node_key_and_child_count.json");
+
+    /// The document without metadata, with node keys in place of the values
+    fn node_keys(node: &MetaNode) -> serde_json::Value {
+        use serde_json::Value;
+        match node {
+            MetaNode::Object(object) => Value::Object(
+                object
+                    .value
+                    .iter()
+                    .map(|member| match member {
+                        MetaNode::ObjectKey(object_key) => {
+                            (object_key.key.clone(), node_keys(&object_key.value))
+                        }
+                        member => panic!("expected object key, got {:?}", member),
+                    })
+                    .collect(),
+            ),
+            MetaNode::Array(array) => Value::Array(array.value.iter().map(node_keys).collect()),
+            MetaNode::ObjectKey(object_key) => panic!("unexpected object key {:?}", object_key),
+            MetaNode::String(node) => node.metadata.node_key.into(),
+            MetaNode::Number(node) => node.metadata.node_key.into(),
+            MetaNode::Bool(node) => node.metadata.node_key.into(),
+            MetaNode::Null(node) => node.metadata.node_key.into(),
+        }
+    }
+
+    #[test]
+    fn test_metadata_shapes() {
+        let expected = serde_json::json!({"foo": [4, 5, 6], "baz": 8, "empty": {}, "list": []});
+        for json in &[
+            METADATA_ALL,
+            METADATA_NODE_KEY,
+            METADATA_NODE_KEY_AND_CHILD_COUNT,
+        ] {
+            let node: MetaNode = serde_json::from_str(json).unwrap();
+            assert_eq!(node_keys(&node), expected);
+        }
+    }
+
+    #[test]
+    fn test_metadata_all() {
+        let node: MetaNode = serde_json::from_str(METADATA_ALL).unwrap();
+        let object = match node {
+            MetaNode::Object(object) => object,
+            node => panic!("expected object, got {:?}", node),
+        };
+        assert_eq!(object.metadata.node_type, Some(NodeTypeContainer::Object));
+        assert_eq!(object.metadata.hash.as_deref(), Some("6b0e3a5d1c9f2e47"));
+        assert_eq!(object.metadata.descendant_count, Some(11));
+        assert_eq!(object.metadata.child_count, Some(4));
+        match &object.value[1] {
+            MetaNode::ObjectKey(object_key) => {
+                assert_eq!(object_key.key, "baz");
+                assert_eq!(
+                    object_key.metadata.node_type,
+                    Some(NodeTypePrimitive::ObjectKey)
+                );
+                assert_eq!(object_key.metadata.descendant_count, Some(1));
+                match &*object_key.value {
+                    MetaNode::Bool(node) => {
+                        assert!(!node.value);
+                        assert_eq!(
+                            node.metadata.node_type,
+                            Some(NodeTypePrimitive::ObjectBooleanValue)
+                        );
+                    }
+                    node => panic!("expected bool, got {:?}", node),
+                }
+            }
+            node => panic!("expected object key, got {:?}", node),
+        }
+    }
+
+    #[test]
+    fn test_metadata_node_key_and_child_count() {
+        let node: MetaNode = serde_json::from_str(METADATA_NODE_KEY_AND_CHILD_COUNT).unwrap();
+        match node {
+            MetaNode::Object(object) => {
+                assert_eq!(object.metadata.node_type, None);
+                assert_eq!(object.metadata.hash, None);
+                assert_eq!(object.metadata.child_count, Some(4));
+            }
+            node => panic!("expected object, got {:?}", node),
+        }
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        for json in &[
+            METADATA_ALL,
+            METADATA_NODE_KEY,
+            METADATA_NODE_KEY_AND_CHILD_COUNT,
+        ] {
+            let node: MetaNode = serde_json::from_str(json).unwrap();
+            assert_eq!(
+                serde_json::to_value(&node).unwrap(),
+                serde_json::from_str::<serde_json::Value>(json).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_metadata_numeric_hash() {
+        let json = r#"{"metadata":{"nodeKey":4,"hash":5477671295884624565,"type":"STRING_VALUE"},"value":"bar"}"#;
+        match serde_json::from_str(json).unwrap() {
+            MetaNode::String(node) => {
+                assert_eq!(node.metadata.hash.as_deref(), Some("5477671295884624565"))
+            }
+            node => panic!("expected string, got {:?}", node),
+        }
+    }
+
+    #[test]
+    fn test_metadata_invalid() {
+        for json in &[
+            r#""""#,
+            "{}",
+            r#"{"value":"bar"}"#,
+            r#"{"metadata":{"nodeKey":1,"type":"ARRAY"},"value":"bar"}"#,
+            r#"{"metadata":{"nodeKey":1,"type":"STRING_VALUE"},"value":[]}"#,
+            r#"{"metadata":{"nodeKey":1},"key":"foo","value":"bar"}"#,
+        ] {
+            assert!(serde_json::from_str::<MetaNode>(json).is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn test_meta_node_navigation() {
+        let node: MetaNode = serde_json::from_str(METADATA_ALL).unwrap();
+        assert_eq!(node.node_key(), 1);
+        assert_eq!(node.hash(), Some("6b0e3a5d1c9f2e47"));
+        assert_eq!(node.children().len(), 4);
+        assert_eq!(node.children()[0].children()[0].node_key(), 3);
+
+        let foo = node.get("foo").unwrap();
+        assert_eq!(foo.node_key(), 3);
+        assert_eq!(foo.index(2).unwrap().node_key(), 6);
+        assert!(foo.index(3).is_none());
+        assert!(foo.get("bar").is_none());
+        assert!(node.get("missing").is_none());
+        assert!(node.index(0).is_none());
+
+        // object keys are found as well
+        match node.find_by_node_key(7) {
+            Some(MetaNode::ObjectKey(object_key)) => assert_eq!(object_key.key, "baz"),
+            found => panic!("expected object key, got {:?}", found),
+        }
+        assert_eq!(node.find_by_node_key(12).unwrap().children().len(), 0);
+        assert!(node.find_by_node_key(13).is_none());
+
+        let keys: Vec<usize> = node
+            .into_children()
+            .iter()
+            .map(MetaNode::node_key)
+            .collect();
+        assert_eq!(keys, vec![2, 7, 9, 11]);
+    }
+
+    #[test]
+    fn test_meta_node_iter() {
+        use PathSegment::*;
+        let node: MetaNode = serde_json::from_str(METADATA_NODE_KEY).unwrap();
+        let visited: Vec<(Vec<PathSegment>, usize)> = node
+            .iter()
+            .map(|(path, node)| (path, node.node_key()))
+            .collect();
+        let key = |key: &str| Key(key.to_owned());
+        assert_eq!(
+            visited,
+            vec![
+                (vec![], 1),
+                (vec![key("foo")], 3),
+                (vec![key("foo"), Index(0)], 4),
+                (vec![key("foo"), Index(1)], 5),
+                (vec![key("foo"), Index(2)], 6),
+                (vec![key("baz")], 8),
+                (vec![key("empty")], 10),
+                (vec![key("list")], 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_meta_node_into_value() {
+        for json in &[
+            METADATA_ALL,
+            METADATA_NODE_KEY,
+            METADATA_NODE_KEY_AND_CHILD_COUNT,
+        ] {
+            let node: MetaNode = serde_json::from_str(json).unwrap();
+            assert_eq!(
+                serde_json::Value::from(node),
+                serde_json::json!({"foo": ["bar", null, 2.33], "baz": false, "empty": {}, "list": []})
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_path() {
+        use PathSegment::*;
+        let key = |key: &str| Key(key.to_owned());
+        assert_eq!(parse_path("").unwrap(), vec![]);
+        assert_eq!(parse_path("foo").unwrap(), vec![key("foo")]);
+        assert_eq!(
+            parse_path("a.b[3].c").unwrap(),
+            vec![key("a"), key("b"), Index(3), key("c")]
+        );
+        assert_eq!(
+            parse_path("[0][12].a").unwrap(),
+            vec![Index(0), Index(12), key("a")]
+        );
+        assert_eq!(
+            parse_path("/a/3/b~1c~0d").unwrap(),
+            vec![key("a"), key("3"), key("b/c~d")]
+        );
+        assert_eq!(parse_path("/").unwrap(), vec![key("")]);
+        for path in &[
+            ".a", "a.", "a..b", "a.[0]", "a[", "a[]", "a[x]", "a[+1]", "a[0]b", "/a~2", "/a~",
+        ] {
+            assert_eq!(parse_path(path), Err(InvalidPath(path.to_string())));
+        }
+    }
+
+    #[test]
+    fn test_meta_node_at_path() {
+        let node: MetaNode = serde_json::from_str(METADATA_NODE_KEY).unwrap();
+        let at = |path: &str| {
+            node.at_path(&parse_path(path).unwrap())
+                .map(MetaNode::node_key)
+        };
+        let member = |path: &str| {
+            node.member_at_path(&parse_path(path).unwrap())
+                .map(MetaNode::node_key)
+        };
+        assert_eq!(at(""), Some(1));
+        assert_eq!(at("foo[1]"), Some(5));
+        assert_eq!(at("/foo/2"), Some(6));
+        assert_eq!(at("baz"), Some(8));
+        assert_eq!(at("foo[3]"), None);
+        assert_eq!(at("/foo/x"), None);
+        assert_eq!(at("baz.x"), None);
+        assert_eq!(member("baz"), Some(7));
+        assert_eq!(member("/empty"), Some(9));
+        assert_eq!(member("foo[1]"), Some(5));
+        assert_eq!(member(""), Some(1));
+        assert_eq!(member("missing"), None);
+    }
+}
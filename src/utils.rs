@@ -1,4 +1,10 @@
-use super::types::{ReadArgs, RevisionArg, SingleRevision, TwoRevisions};
+use super::timestamp::{format_timestamp, Timestamp};
+use super::types::{
+    Commit, DiffArgs, History, HistoryQuery, Query, ReadArgs, RevisionArg, SingleRevision,
+    TwoRevisions, UnexpectedQueryResult, WriteOptions,
+};
+use serde_json::Value;
+use std::cmp::Reverse;
 
 pub fn build_read_params(read_args: ReadArgs) -> Vec<(String, String)> {
     let mut params: Vec<(String, String)> = Vec::new();
@@ -29,30 +35,256 @@ pub fn build_read_params(read_args: ReadArgs) -> Vec<(String, String)> {
         }
         None => (),
     };
-    match read_args.revision {
-        Some(revision) => match revision {
-            RevisionArg::SingleRevision(revision) => match revision {
-                SingleRevision::Number(revision) => {
-                    params.push(("revision".to_owned(), revision.to_string()));
-                }
-                SingleRevision::Timestamp(revision) => {
-                    params.push(("revision-timestamp".to_owned(), revision.to_string()));
-                }
-            },
-            RevisionArg::TwoRevisions(revisions) => {
-                match revisions {
-                    TwoRevisions::Number(first_revision, second_revision) => {
-                        params.push(("start-revision".to_owned(), first_revision.to_string()));
-                        params.push(("end-revision".to_owned(), second_revision.to_string()));
-                    }
-                    TwoRevisions::Timestamp(first_revision, second_revision) => {
-                        params.push(("start-revision-timestamp".to_owned(), first_revision));
-                        params.push(("end-revision-timestamp".to_owned(), second_revision));
-                    }
-                };
+    if let Some(revision) = read_args.revision {
+        push_revision_params(&mut params, revision);
+    }
+    return params;
+}
+
+pub fn build_diff_params(diff_args: DiffArgs) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = Vec::new();
+    if let Some(node_id) = diff_args.node_id {
+        params.push(("startNodeKey".to_owned(), node_id.to_string()));
+    }
+    if let Some(max_depth) = diff_args.max_depth {
+        params.push(("maxDepth".to_owned(), max_depth.to_string()));
+    }
+    match diff_args.first_revision {
+        SingleRevision::Timestamp(revision) => {
+            params.push(("first-revision".to_owned(), format_timestamp(&revision)))
+        }
+        SingleRevision::Number(revision) => {
+            params.push(("first-revision".to_owned(), revision.to_string()))
+        }
+    }
+    match diff_args.second_revision {
+        SingleRevision::Timestamp(revision) => {
+            params.push(("second-revision".to_owned(), format_timestamp(&revision)))
+        }
+        SingleRevision::Number(revision) => {
+            params.push(("second-revision".to_owned(), revision.to_string()))
+        }
+    };
+    params
+}
+
+pub fn build_write_params(options: &WriteOptions) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = Vec::new();
+    if let Some(commit_message) = &options.commit_message {
+        params.push(("commitMessage".to_owned(), commit_message.clone()));
+    }
+    if let Some(commit_timestamp) = &options.commit_timestamp {
+        params.push((
+            "commitTimestamp".to_owned(),
+            format_timestamp(commit_timestamp),
+        ));
+    }
+    params
+}
+
+/// The params of `/history`, which takes either the number of most recent commits
+/// as `revisions`, or a range of revisions as `startRevision` and `endRevision`.
+///
+/// A limit on a range of revisions narrows the range to its most recent revisions.
+/// `/history` cannot select commits by timestamp, so a timestamp range asks for
+/// the whole history, to be narrowed down by `select_history`.
+pub fn build_history_params(query: &HistoryQuery) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = Vec::new();
+    match (&query.range, query.limit) {
+        (Some(TwoRevisions::Number(start, end)), limit) => {
+            let start = match limit {
+                Some(limit) => (*start).max((end + 1).saturating_sub(limit)),
+                None => *start,
+            };
+            params.push(("startRevision".to_owned(), start.to_string()));
+            params.push(("endRevision".to_owned(), end.to_string()));
+        }
+        (Some(TwoRevisions::Timestamp(..)), _) => (),
+        (None, Some(limit)) => params.push(("revisions".to_owned(), limit.to_string())),
+        (None, None) => (),
+    }
+    params
+}
+
+/// The commits of `history` selected by `query`, oldest first
+pub fn select_history(history: History, query: &HistoryQuery) -> History {
+    let mut commits = history_in_range(history, query.range.as_ref());
+    if let Some(limit) = query.limit {
+        commits.truncate(limit as usize);
+    }
+    commits.reverse();
+    History { history: commits }
+}
+
+/// The query for the commits `commit_at` picks `revision` from.
+///
+/// A timestamp needs the whole history, since `/history` cannot select commits by timestamp.
+pub fn revision_history_query(revision: &SingleRevision) -> HistoryQuery {
+    match revision {
+        SingleRevision::Number(revision) => HistoryQuery {
+            range: Some(TwoRevisions::Number(*revision, *revision)),
+            limit: None,
+        },
+        SingleRevision::Timestamp(_) => HistoryQuery::default(),
+    }
+}
+
+/// The commit of `history` that created `revision`, or the latest one committed
+/// at or before its timestamp, whatever order `history` is in
+pub fn commit_at(history: History, revision: &SingleRevision) -> Option<Commit> {
+    match revision {
+        SingleRevision::Number(revision) => history
+            .into_iter()
+            .find(|commit| commit.revision as u64 == *revision),
+        SingleRevision::Timestamp(timestamp) => history
+            .into_iter()
+            .filter(|commit| &commit.revision_timestamp <= timestamp)
+            .max_by(|a, b| {
+                a.revision_timestamp
+                    .cmp(&b.revision_timestamp)
+                    .then(a.revision.cmp(&b.revision))
+            }),
+    }
+}
+
+/// The commits of `history` in `range`, newest first
+pub fn history_in_range(history: History, range: Option<&TwoRevisions>) -> Vec<Commit> {
+    let mut commits: Vec<Commit> = history
+        .into_iter()
+        .filter(|commit| match range {
+            Some(TwoRevisions::Number(start, end)) => {
+                (*start..=*end).contains(&(commit.revision as u64))
+            }
+            Some(TwoRevisions::Timestamp(start, end)) => {
+                (start..=end).contains(&&commit.revision_timestamp)
             }
+            None => true,
+        })
+        .collect();
+    commits.sort_by_key(|commit| Reverse(commit.revision));
+    commits
+}
+
+/// Whether `history` goes back past `since`, so older pages have no commits after it
+pub fn history_reaches(history: &History, since: Option<&Timestamp>) -> bool {
+    match since {
+        Some(since) => history
+            .iter()
+            .any(|commit| &commit.revision_timestamp < since),
+        None => false,
+    }
+}
+
+/// The query for a page of history of at most `size` commits, from revision `newest`
+/// back to revision `oldest`, or of the `size` most recent commits if `newest` is unknown
+pub fn history_page_query(newest: Option<u64>, oldest: u64, size: u64) -> HistoryQuery {
+    match newest {
+        Some(newest) => HistoryQuery {
+            range: Some(TwoRevisions::Number(
+                oldest.max((newest + 1).saturating_sub(size)),
+                newest,
+            )),
+            limit: None,
+        },
+        None => HistoryQuery {
+            range: None,
+            limit: Some(size),
         },
-        None => {}
+    }
+}
+
+/// The newest revision of the page of history after the one `history` answered
+/// `query` with, or `None` once revision `oldest` was reached
+pub fn history_next_newest(query: &HistoryQuery, history: &History, oldest: u64) -> Option<u64> {
+    let start = match (&query.range, query.limit) {
+        (Some(TwoRevisions::Number(start, _)), _) => *start,
+        (_, Some(limit)) if history.len() as u64 >= limit => {
+            history.iter().map(|commit| commit.revision as u64).min()?
+        }
+        _ => return None,
     };
-    return params;
+    if start > oldest {
+        Some(start - 1)
+    } else {
+        None
+    }
+}
+
+pub fn build_query_params(query: Query, revision: Option<RevisionArg>) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = vec![("query".to_owned(), query.query)];
+    if let Some(start_result_seq_index) = query.start_result_seq_index {
+        params.push((
+            "startResultSeqIndex".to_owned(),
+            start_result_seq_index.to_string(),
+        ));
+    }
+    if let Some(end_result_seq_index) = query.end_result_seq_index {
+        params.push((
+            "endResultSeqIndex".to_owned(),
+            end_result_seq_index.to_string(),
+        ));
+    }
+    if let Some(revision) = revision {
+        push_revision_params(&mut params, revision);
+    }
+    params
+}
+
+pub fn build_revision_params(revision: Option<RevisionArg>) -> Vec<(String, String)> {
+    let mut params: Vec<(String, String)> = Vec::new();
+    if let Some(revision) = revision {
+        push_revision_params(&mut params, revision);
+    }
+    params
+}
+
+fn push_revision_params(params: &mut Vec<(String, String)>, revision: RevisionArg) {
+    match revision {
+        RevisionArg::SingleRevision(revision) => match revision {
+            SingleRevision::Number(revision) => {
+                params.push(("revision".to_owned(), revision.to_string()));
+            }
+            SingleRevision::Timestamp(revision) => {
+                params.push(("revision-timestamp".to_owned(), format_timestamp(&revision)));
+            }
+        },
+        RevisionArg::TwoRevisions(revisions) => push_range_params(params, revisions),
+    }
+}
+
+fn push_range_params(params: &mut Vec<(String, String)>, revisions: TwoRevisions) {
+    match revisions {
+        TwoRevisions::Number(first_revision, second_revision) => {
+            params.push(("start-revision".to_owned(), first_revision.to_string()));
+            params.push(("end-revision".to_owned(), second_revision.to_string()));
+        }
+        TwoRevisions::Timestamp(first_revision, second_revision) => {
+            params.push((
+                "start-revision-timestamp".to_owned(),
+                format_timestamp(&first_revision),
+            ));
+            params.push((
+                "end-revision-timestamp".to_owned(),
+                format_timestamp(&second_revision),
+            ));
+        }
+    }
+}
+
+/// Split a page of query results into the items of the result sequence.
+///
+/// SirixDB wraps the results of a paged query in `{"rest": [...]}`,
+/// so any other page is an error rather than a guess at its items.
+pub fn query_result_items(page: Value) -> Result<Vec<Value>, UnexpectedQueryResult> {
+    match page {
+        Value::Object(mut object) if object.len() == 1 => match object.remove("rest") {
+            Some(Value::Array(items)) => Ok(items),
+            Some(rest) => {
+                object.insert("rest".to_owned(), rest);
+                Err(UnexpectedQueryResult(Value::Object(object)))
+            }
+            None => Err(UnexpectedQueryResult(Value::Object(object))),
+        },
+        page => Err(UnexpectedQueryResult(page)),
+    }
 }
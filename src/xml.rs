@@ -0,0 +1,233 @@
+//! Parsing XML resources.
+//!
+//! Documents are parsed into `minidom::Element`s, or deserialized with quick-xml.
+//! When read with metadata, SirixDB adds `rest:` attributes to the elements,
+//! which `XmlMetaNode` makes available.
+
+use minidom::Element;
+use serde::de::DeserializeOwned;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// An XML response that could not be parsed
+#[derive(Error, Debug)]
+pub enum XmlError {
+    #[error(transparent)]
+    Parse(#[from] minidom::Error),
+    #[error(transparent)]
+    Deserialize(#[from] quick_xml::DeError),
+    #[error("invalid {attribute} attribute: {value:?}")]
+    InvalidMetadata { attribute: String, value: String },
+}
+
+/// Parse an XML document.
+///
+/// minidom requires every element to have a namespace, so documents whose
+/// elements have none are parsed with the empty namespace as default.
+pub fn parse_element(xml: &str) -> Result<Element, XmlError> {
+    match Element::from_str(xml) {
+        Err(minidom::Error::MissingNamespace) => {
+            let wrapped = format!(r#"<wrapper xmlns="">{}</wrapper>"#, strip_declaration(xml));
+            let wrapper = Element::from_str(&wrapped)?;
+            wrapper
+                .children()
+                .next()
+                .cloned()
+                .ok_or(XmlError::Parse(minidom::Error::EndOfDocument))
+        }
+        parsed => Ok(parsed?),
+    }
+}
+
+/// Deserialize an XML document with quick-xml
+pub fn deserialize<T: DeserializeOwned>(xml: &str) -> Result<T, XmlError> {
+    Ok(quick_xml::de::from_str(xml)?)
+}
+
+/// The document without its `<?xml ...?>` declaration, which may only come first
+fn strip_declaration(xml: &str) -> &str {
+    let trimmed = xml.trim_start();
+    match trimmed.strip_prefix("<?xml") {
+        Some(rest) => match rest.find("?>") {
+            Some(end) => &rest[end + 2..],
+            None => xml,
+        },
+        None => xml,
+    }
+}
+
+/// The metadata SirixDB adds to an element as `rest:` attributes.
+///
+/// Only `rest:id` is always present, the others depend on the `MetadataType`
+/// of the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlMetadata {
+    /// `rest:id`, to use as `node_id` in `update` and `delete`
+    pub node_key: u64,
+    /// `rest:hash`
+    pub hash: Option<String>,
+    /// `rest:descendantCount`
+    pub descendant_count: Option<u64>,
+    /// `rest:childCount`
+    pub child_count: Option<u64>,
+}
+
+impl XmlMetadata {
+    /// The metadata of `element`, or `None` if it has no `rest:id` attribute
+    pub fn of(element: &Element) -> Result<Option<Self>, XmlError> {
+        let node_key = match count_attr(element, "rest:id")? {
+            Some(node_key) => node_key,
+            None => return Ok(None),
+        };
+        Ok(Some(XmlMetadata {
+            node_key,
+            hash: element.attr("rest:hash").map(String::from),
+            descendant_count: count_attr(element, "rest:descendantCount")?,
+            child_count: count_attr(element, "rest:childCount")?,
+        }))
+    }
+}
+
+fn count_attr(element: &Element, attribute: &str) -> Result<Option<u64>, XmlError> {
+    element
+        .attr(attribute)
+        .map(|value| {
+            value.parse().map_err(|_| XmlError::InvalidMetadata {
+                attribute: attribute.to_owned(),
+                value: value.to_owned(),
+            })
+        })
+        .transpose()
+}
+
+/// An element of a resource read with metadata
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlMetaNode {
+    pub metadata: XmlMetadata,
+    /// the element as sent, including the `rest:` attributes and all descendants
+    pub element: Element,
+    /// the nodes among the descendants of `element` that have metadata
+    pub children: Vec<XmlMetaNode>,
+}
+
+impl XmlMetaNode {
+    /// The nodes with metadata in `element`, outermost first.
+    ///
+    /// Elements without metadata, such as the `rest:sequence` and `rest:item`
+    /// wrapping the response, are skipped, and their children collected instead.
+    pub fn collect(element: &Element) -> Result<Vec<XmlMetaNode>, XmlError> {
+        match XmlMetadata::of(element)? {
+            Some(metadata) => Ok(vec![XmlMetaNode {
+                metadata,
+                element: element.clone(),
+                children: Self::collect_children(element)?,
+            }]),
+            None => Self::collect_children(element),
+        }
+    }
+
+    fn collect_children(element: &Element) -> Result<Vec<XmlMetaNode>, XmlError> {
+        let mut nodes = Vec::new();
+        for child in element.children() {
+            nodes.extend(Self::collect(child)?);
+        }
+        Ok(nodes)
+    }
+
+    pub fn node_key(&self) -> u64 {
+        self.metadata.node_key
+    }
+
+    /// This node or a descendant with the given node key
+    pub fn find_by_node_key(&self, node_key: u64) -> Option<&XmlMetaNode> {
+        if self.node_key() == node_key {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .find_map(|child| child.find_by_node_key(node_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    const WITH_METADATA: &str = r#"<rest:sequence xmlns:rest="https://sirix.io"><rest:item><shop rest:id="1" rest:hash="3f2a" rest:descendantCount="4" rest:childCount="2"><book rest:id="2" lang="en"><title rest:id="3">Dune</title></book><book rest:id="5" lang="de"/></shop></rest:item></rest:sequence>"#;
+
+    #[test]
+    fn test_parse_without_namespace() {
+        let element =
+            parse_element(r#"<?xml version="1.0"?><shop><book lang="en">Dune</book></shop>"#)
+                .unwrap();
+        assert_eq!(element.name(), "shop");
+        let book = element.children().next().unwrap();
+        assert_eq!(book.attr("lang"), Some("en"));
+        assert_eq!(book.text(), "Dune");
+    }
+
+    #[test]
+    fn test_parse_with_namespace() {
+        let element = parse_element(r#"<shop xmlns="urn:shop"><book/></shop>"#).unwrap();
+        assert_eq!(element.ns(), "urn:shop");
+        assert!(element.has_child("book", "urn:shop"));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse_element("<shop>").is_err());
+        assert!(parse_element("").is_err());
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Book {
+            lang: String,
+            title: String,
+        }
+        let book: Book = deserialize(r#"<book lang="en"><title>Dune</title></book>"#).unwrap();
+        assert_eq!(
+            book,
+            Book {
+                lang: "en".to_owned(),
+                title: "Dune".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn test_meta_nodes() {
+        let element = parse_element(WITH_METADATA).unwrap();
+        let nodes = XmlMetaNode::collect(&element).unwrap();
+        assert_eq!(nodes.len(), 1);
+        let shop = &nodes[0];
+        assert_eq!(
+            shop.metadata,
+            XmlMetadata {
+                node_key: 1,
+                hash: Some("3f2a".to_owned()),
+                descendant_count: Some(4),
+                child_count: Some(2),
+            }
+        );
+        assert_eq!(shop.element.name(), "shop");
+        let keys: Vec<u64> = shop.children.iter().map(XmlMetaNode::node_key).collect();
+        assert_eq!(keys, vec![2, 5]);
+        let title = shop.find_by_node_key(3).unwrap();
+        assert_eq!(title.element.text(), "Dune");
+        assert_eq!(title.metadata.hash, None);
+        assert!(shop.find_by_node_key(4).is_none());
+    }
+
+    #[test]
+    fn test_invalid_metadata() {
+        let element =
+            parse_element(r#"<shop rest:id="one" xmlns:rest="https://sirix.io"/>"#).unwrap();
+        assert!(matches!(
+            XmlMetaNode::collect(&element),
+            Err(XmlError::InvalidMetadata { .. })
+        ));
+    }
+}
@@ -13,9 +13,10 @@ mod asynchronous {
         spawn_client(hyper::Client::new(), receiver);
         let uri = "http://localhost:9443".parse::<Uri>().unwrap();
         // initiate auth coroutine
-        let (watch_rx, _kill_switch) = auth("admin", "admin", &uri.to_string(), sender.clone())
-            .await
-            .unwrap();
+        let (watch_rx, _state_rx, _kill_switch) =
+            auth("admin", "admin", &uri.to_string(), sender.clone())
+                .await
+                .unwrap();
         tokio::time::sleep(Duration::from_millis(1000)).await;
         let sirix = Sirix::new(uri, sender, Some(watch_rx));
         let result = sirix.info_with_resources().await;
@@ -35,8 +36,8 @@ mod synchronous {
         let url = "http://localhost:9443";
         let agent = ureq::agent();
 
-        let lock = auth(agent.clone(), url, "admin", "admin");
-        let sirix = Sirix::new(url.to_string(), agent.clone(), Some(lock));
+        let handle = auth(agent.clone(), url, "admin", "admin").unwrap();
+        let sirix = Sirix::new(url.to_string(), agent.clone(), Some(handle.token()));
         let result = sirix.info_with_resources();
         assert!(result.is_ok());
         let databases = result.unwrap().body.databases;